@@ -0,0 +1,139 @@
+use serde_json::Value;
+
+use crate::expr::Expr;
+use crate::model::text_value;
+
+/// An `--alert` rule: `NAME: CONDITION`, optionally followed by `within Nh`.
+/// Without `within`, the condition is tested against the current conditions,
+/// with it against every hourly row of the next N hours. For example
+/// `rain: chanceofrain > 70 within 3h` or `frost: temp_C < 0`.
+#[derive(Debug, Clone)]
+pub struct Alert {
+    pub name: String,
+    pub rule: String,
+    condition: Expr,
+    within: Option<i64>,
+}
+
+impl Alert {
+    pub fn parse(source: &str, hours_ahead: i64) -> Result<Self, String> {
+        let (name, rule) = source
+            .split_once(':')
+            .ok_or_else(|| format!("{:?} has no name, like rain: {}", source, source))?;
+        let name = name.trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "{:?} is not a name, use letters, digits, - and _",
+                name
+            ));
+        }
+        let rule = rule.trim();
+        let (condition, within) = match rule.rsplit_once(" within ") {
+            Some((condition, hours)) => {
+                let hours = hours
+                    .trim()
+                    .strip_suffix('h')
+                    .and_then(|hours| hours.parse::<i64>().ok())
+                    .ok_or_else(|| {
+                        format!("in {}: within needs hours, like 3h, not {:?}", name, hours)
+                    })?;
+                if !(1..=hours_ahead).contains(&hours) {
+                    return Err(format!(
+                        "in {}: within takes 1 to {} hours, not {}",
+                        name, hours_ahead, hours
+                    ));
+                }
+                (condition, Some(hours))
+            }
+            None => (rule, None),
+        };
+        let condition = Expr::parse(condition).map_err(|err| format!("in {}: {}", name, err))?;
+        Ok(Alert {
+            name: name.to_string(),
+            rule: rule.to_string(),
+            condition,
+            within,
+        })
+    }
+
+    /// The CSS class added while the rule matches.
+    pub fn class(&self) -> String {
+        format!("alert-{}", self.name)
+    }
+
+    /// Whether the rule matches. `current` holds the current conditions,
+    /// `forecast` the `hour+N` rows.
+    pub fn matches(&self, current: &Value, forecast: &Value) -> bool {
+        match self.within {
+            None => self.condition.test(&|key| lookup(current, key)),
+            Some(hours) => (0..=hours).any(|hour| {
+                let row = &forecast[format!("hour+{}", hour)];
+                row.is_object() && self.condition.test(&|key| lookup(row, key))
+            }),
+        }
+    }
+}
+
+/// The value of `key`, with dots separating the keys of nested objects.
+fn lookup(value: &Value, key: &str) -> Option<String> {
+    let value = key
+        .split('.')
+        .try_fold(value, |value, key| value.get(key))?;
+    match value {
+        Value::Number(number) => Some(number.to_string()),
+        value => text_value(value).map(str::to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn matches_current_and_upcoming_hours() {
+        let current = json!({"temp_C": "-1", "uvIndex": "3"});
+        let forecast = json!({
+            "hour+0": {"chanceofrain": "10"},
+            "hour+2": {"chanceofrain": "80"},
+            "hour+5": {"chanceofrain": "90"},
+        });
+
+        let frost = Alert::parse("frost: temp_C < 0", 48).unwrap();
+        assert_eq!(frost.class(), "alert-frost");
+        assert!(frost.matches(&current, &forecast));
+        assert!(!Alert::parse("uv: uvIndex >= 8", 48)
+            .unwrap()
+            .matches(&current, &forecast));
+
+        let rain = Alert::parse("rain: chanceofrain > 70 within 3h", 48).unwrap();
+        assert_eq!(rain.rule, "chanceofrain > 70 within 3h");
+        assert!(rain.matches(&current, &forecast));
+        let later = Alert::parse("rain: chanceofrain > 85 within 3h", 48).unwrap();
+        assert!(!later.matches(&current, &forecast));
+    }
+
+    #[test]
+    fn reports_invalid_rules() {
+        assert!(Alert::parse("temp_C < 0", 48).is_err());
+        assert!(Alert::parse("bad name: temp_C < 0", 48).is_err());
+        assert!(Alert::parse("rain: chanceofrain > 70 within 3", 48).is_err());
+        assert_eq!(
+            Alert::parse("rain: chanceofrain > 70 within 72h", 48).unwrap_err(),
+            "in rain: within takes 1 to 48 hours, not 72"
+        );
+        assert_eq!(
+            Alert::parse("rain: chanceofrain > 70 within 0h", 48).unwrap_err(),
+            "in rain: within takes 1 to 48 hours, not 0"
+        );
+        assert_eq!(
+            Alert::parse("rain: chanceofrain > 70 within -3h", 48).unwrap_err(),
+            "in rain: within takes 1 to 48 hours, not -3"
+        );
+        assert!(Alert::parse("rain: chanceofrain >", 48).is_err());
+    }
+}
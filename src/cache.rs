@@ -0,0 +1,124 @@
+use std::env;
+use std::fs::{self, metadata, read_to_string};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::format::percent_encode;
+use crate::model::Weather;
+
+/// Forecasts stored on disk, one file per provider and location, so Waybar
+/// restarts and short intervals don't hit the provider every time.
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+    max_age: Duration,
+}
+
+impl Cache {
+    pub fn new(dir: PathBuf, ttl: Duration, max_age: Duration) -> Self {
+        Cache { dir, ttl, max_age }
+    }
+
+    /// The cached forecast stored under `name` and when it was written, if it
+    /// is younger than the TTL.
+    pub fn read(&self, name: &str) -> Option<(Weather, SystemTime)> {
+        self.read_younger_than(name, self.ttl)
+    }
+
+    /// Like [`Cache::read`], but accepts anything up to the maximum age. Used
+    /// when a new forecast can't be fetched.
+    pub fn read_stale(&self, name: &str) -> Option<(Weather, SystemTime)> {
+        self.read_younger_than(name, self.max_age)
+    }
+
+    fn read_younger_than(&self, name: &str, age: Duration) -> Option<(Weather, SystemTime)> {
+        let path = self.dir.join(name);
+        let mod_time = metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()?;
+        if mod_time.elapsed().unwrap_or_default() > age {
+            return None;
+        }
+
+        read_to_string(&path)
+            .ok()
+            .and_then(|json_str| serde_json::from_str::<Weather>(&json_str).ok())
+            .map(|weather| (weather, mod_time))
+    }
+
+    pub fn write(&self, name: &str, weather: &Weather) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // Write to a temporary file first, so nobody reads a half written
+        // forecast.
+        let path = self.dir.join(name);
+        let tmp_path = self.dir.join(format!("{}.tmp", name));
+        fs::write(&tmp_path, serde_json::to_string_pretty(weather)?)?;
+        fs::rename(&tmp_path, &path)
+    }
+}
+
+/// `$XDG_CACHE_HOME/wttrbar`, falling back to `~/.cache/wttrbar`.
+pub fn default_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("XDG_CACHE_HOME").map(PathBuf::from) {
+        if dir.is_absolute() {
+            return dir.join("wttrbar");
+        }
+    }
+    if let Some(home) = env::var_os("HOME").filter(|home| !home.is_empty()) {
+        return PathBuf::from(home).join(".cache").join("wttrbar");
+    }
+    env::temp_dir().join("wttrbar")
+}
+
+/// Name of the cache file for `location` as fetched by the provider with
+/// `key`. Locations may contain anything from `/` to spaces, so everything
+/// but a few safe characters is percent-encoded.
+pub fn file_name(key: &str, location: &str) -> String {
+    format!("{}-{}.json", encode(key, true), encode(location, false))
+}
+
+fn encode(part: &str, allow_dash: bool) -> String {
+    let mut encoded = percent_encode(part, |byte| {
+        byte.is_ascii_alphanumeric() || b"_.,".contains(&byte) || (allow_dash && byte == b'-')
+    });
+    // Don't let a location like ".." become a special file name.
+    if encoded.chars().all(|c| c == '.') {
+        encoded = encoded.replace('.', "%2E");
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_simple_locations() {
+        assert_eq!(file_name("wttr.in", "Amsterdam"), "wttr.in-Amsterdam.json");
+        assert_eq!(
+            file_name("de.wttr.in", "52.37,4.89"),
+            "de.wttr.in-52.37,4.89.json"
+        );
+        assert_eq!(file_name("wttr.in", ""), "wttr.in-.json");
+    }
+
+    #[test]
+    fn encodes_unsafe_characters() {
+        assert_eq!(file_name("wttr.in", "New York"), "wttr.in-New%20York.json");
+        assert_eq!(
+            file_name("wttr.in", "~Eiffel/Tower"),
+            "wttr.in-%7EEiffel%2FTower.json"
+        );
+        assert_eq!(
+            file_name("wttr.in", "São Paulo"),
+            "wttr.in-S%C3%A3o%20Paulo.json"
+        );
+        assert_eq!(file_name("wttr.in", ".."), "wttr.in-%2E%2E.json");
+    }
+
+    #[test]
+    fn dashes_in_locations_are_not_ambiguous() {
+        assert_ne!(file_name("a-b", "c"), file_name("a", "b-c"));
+    }
+}
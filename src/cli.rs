@@ -1,3 +1,10 @@
+use std::path::PathBuf;
+
+use crate::provider::ProviderKind;
+use crate::render::TooltipField;
+use crate::units::{
+    DistanceUnit, PrecipitationUnit, PressureUnit, TemperatureUnit, UnitSystem, WindUnit,
+};
 use crate::Lang;
 use clap::Parser;
 
@@ -8,6 +15,15 @@ about = "A simple but detailed weather indicator for Waybar using wttr.in",
 long_about = None)
 ]
 pub struct Args {
+    #[arg(
+        long,
+        help = "read default options from this TOML file instead of $XDG_CONFIG_HOME/wttrbar/config.toml"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, help = "use the options in [profiles.NAME] of the config file")]
+    pub profile: Option<String>,
+
     #[arg(
         long,
         default_value = "temp_C",
@@ -29,8 +45,55 @@ pub struct Args {
     )]
     pub date_format: String,
 
-    #[arg(long, help = "pass a specific location to wttr.in")]
-    pub location: Option<String>,
+    #[arg(
+        long,
+        help = "pass a specific location to wttr.in. repeat it to list more locations in the tooltip"
+    )]
+    pub location: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "DECIMALS",
+        default_value_t = 2,
+        value_parser = clap::value_parser!(u8).range(0..=6),
+        help = "decimals the position from --location auto-geoclue is rounded to before it's sent to the provider"
+    )]
+    pub geoclue_precision: u8,
+
+    #[arg(
+        long,
+        help = "country the location should be in, to warn when it resolves elsewhere"
+    )]
+    pub country: Option<String>,
+
+    #[arg(
+        long,
+        help = "region or state the location should be in, to warn when it resolves elsewhere"
+    )]
+    pub region: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KM",
+        default_value_t = 25.0,
+        help = "how far the resolved place may be from the requested lat,lon before a warning"
+    )]
+    pub location_tolerance: f64,
+
+    #[arg(
+        long,
+        env = "WTTRBAR_BASE_URL",
+        help = "use a different wttr.in instance, e.g. http://localhost:8002. the language is passed with the lang= query parameter"
+    )]
+    pub base_url: Option<String>,
+
+    #[arg(
+        value_enum,
+        long,
+        default_value = "wttr-in",
+        help = "weather data source. open-meteo needs --location"
+    )]
+    pub provider: ProviderKind,
 
     #[arg(
         long,
@@ -44,6 +107,55 @@ pub struct Args {
     )]
     pub hide_conditions: bool,
 
+    #[arg(
+        long,
+        help = "replace the tooltip with a template, or read it from a file with @PATH. {key} is replaced by a value, {#days}...{/days} and {#hours}...{/hours} repeat their content"
+    )]
+    pub tooltip_template: Option<String>,
+
+    #[arg(
+        long,
+        requires = "daemon",
+        help = "send desktop notifications when rain or snow is about to start or an --alert rule matches"
+    )]
+    pub notify: bool,
+
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        default_value_t = 50,
+        value_parser = clap::value_parser!(u8).range(1..=100),
+        help = "chance of rain or snow in the next hour that --notify reports"
+    )]
+    pub notify_chance: u8,
+
+    #[arg(
+        long,
+        value_name = "RULE",
+        help = "show a warning when a rule like \"rain: chanceofrain > 70 within 3h\" matches, can be repeated"
+    )]
+    pub alert: Vec<String>,
+
+    #[arg(
+        value_enum,
+        long,
+        value_delimiter = ',',
+        help = "extra rows for the current conditions in the tooltip, comma separated"
+    )]
+    pub tooltip_fields: Vec<TooltipField>,
+
+    #[arg(
+        long,
+        help = "use the lowercased weather description as the CSS class, like older versions"
+    )]
+    pub legacy_class: bool,
+
+    #[arg(
+        long,
+        help = "show times in this computer's time zone instead of the forecast location's"
+    )]
+    pub local_time: bool,
+
     #[arg(long, help = "display time in AM/PM format")]
     pub ampm: bool,
 
@@ -56,6 +168,29 @@ pub struct Args {
     #[arg(long, short, help = "use mph instead of km/h for wind speed")]
     pub mph: bool,
 
+    #[arg(
+        value_enum,
+        long,
+        default_value = "metric",
+        help = "unit system for all measurements. uk uses mph and miles, si uses m/s"
+    )]
+    pub units: UnitSystem,
+
+    #[arg(value_enum, long, help = "temperature unit, overrides --units")]
+    pub temperature_unit: Option<TemperatureUnit>,
+
+    #[arg(value_enum, long, help = "wind speed unit, overrides --units")]
+    pub wind_unit: Option<WindUnit>,
+
+    #[arg(value_enum, long, help = "pressure unit, overrides --units")]
+    pub pressure_unit: Option<PressureUnit>,
+
+    #[arg(value_enum, long, help = "precipitation unit, overrides --units")]
+    pub precipitation_unit: Option<PrecipitationUnit>,
+
+    #[arg(value_enum, long, help = "visibility unit, overrides --units")]
+    pub visibility_unit: Option<DistanceUnit>,
+
     #[arg(value_enum, short, long, help = "language to use")]
     pub lang: Option<Lang>,
 
@@ -64,4 +199,61 @@ pub struct Args {
 
     #[arg(long, help = "replace spaces with tabs in tooltip_line")]
     pub tabs: bool,
+
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "seconds to wait for a connection to the weather provider"
+    )]
+    pub connect_timeout: u64,
+
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "seconds to wait for a complete response from the weather provider"
+    )]
+    pub timeout: u64,
+
+    #[arg(
+        long,
+        default_value_t = 30,
+        help = "seconds to keep retrying when the weather provider can't be reached"
+    )]
+    pub retry_max_time: u64,
+
+    #[arg(
+        long,
+        help = "directory for cached responses. defaults to $XDG_CACHE_HOME/wttrbar"
+    )]
+    pub cache_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = 600,
+        help = "seconds a cached response is used before fetching a new one"
+    )]
+    pub cache_ttl: u64,
+
+    #[arg(
+        long,
+        default_value_t = 21600,
+        help = "seconds an outdated cached response is still shown when the weather can't be fetched"
+    )]
+    pub cache_max_age: u64,
+
+    #[arg(long, help = "always fetch the weather and don't write a cache file")]
+    pub no_cache: bool,
+
+    #[arg(
+        long,
+        help = "keep running and print a new line every minute, for use without an interval in Waybar. SIGUSR1 refreshes the weather, SIGUSR2 cycles the bar text"
+    )]
+    pub daemon: bool,
+
+    #[arg(
+        long,
+        default_value_t = 600,
+        help = "seconds between weather updates in daemon mode"
+    )]
+    pub interval: u64,
 }
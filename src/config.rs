@@ -0,0 +1,216 @@
+use std::env;
+use std::ffi::OsString;
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgAction, ArgMatches, Command, CommandFactory, FromArgMatches};
+use toml::{Table, Value};
+
+use crate::cli::Args;
+use crate::error::Error;
+
+/// Options that only make sense on the command line.
+const CLI_ONLY: &[&str] = &["config", "profile", "help", "version"];
+
+/// Parse the command line, filling in every option it doesn't set from the
+/// config file. Errors in the command line itself are reported by clap as
+/// usual, errors in the config file are returned so they can be shown in
+/// Waybar.
+pub fn parse_args() -> Result<Args, Error> {
+    let command = Args::command();
+    let cli: Vec<OsString> = env::args_os().collect();
+    let matches = command.clone().get_matches_from(&cli);
+
+    let table = match matches.get_one::<PathBuf>("config") {
+        Some(path) => read(path)?,
+        None => {
+            let path = default_path();
+            if path.exists() {
+                read(&path)?
+            } else {
+                Table::new()
+            }
+        }
+    };
+    let values = select_profile(table, matches.get_one::<String>("profile"))?;
+
+    let mut argv = vec![cli[0].clone()];
+    argv.extend(
+        config_args(&command, &matches, values)?
+            .into_iter()
+            .map(OsString::from),
+    );
+    argv.extend(cli.into_iter().skip(1));
+    command
+        .try_get_matches_from(argv)
+        .and_then(|matches| Args::from_arg_matches(&matches))
+        .map_err(|err| {
+            let message = err.to_string();
+            let first_line = message.lines().next().unwrap_or_default();
+            Error::Config(format!(
+                "config: {}",
+                first_line.trim_start_matches("error: ")
+            ))
+        })
+}
+
+/// `$XDG_CONFIG_HOME/wttrbar/config.toml`, falling back to
+/// `~/.config/wttrbar/config.toml`.
+pub fn default_path() -> PathBuf {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    config_home.join("wttrbar").join("config.toml")
+}
+
+fn read(path: &Path) -> Result<Table, Error> {
+    let content = read_to_string(path)
+        .map_err(|err| Error::Config(format!("{}: {}", path.display(), err)))?;
+    content
+        .parse::<Table>()
+        .map_err(|err| Error::Config(format!("{}: {}", path.display(), err.message())))
+}
+
+/// The top level values, overridden by the ones in `[profiles.NAME]`.
+fn select_profile(mut table: Table, profile: Option<&String>) -> Result<Table, Error> {
+    let profiles = match table.remove("profiles") {
+        Some(Value::Table(profiles)) => profiles,
+        Some(_) => {
+            return Err(Error::Config(
+                "config: profiles must be a table".to_string(),
+            ))
+        }
+        None => Table::new(),
+    };
+    if let Some(name) = profile {
+        match profiles.get(name) {
+            Some(Value::Table(values)) => table.extend(values.clone()),
+            _ => return Err(Error::Config(format!("config: no profile named {}", name))),
+        }
+    }
+    Ok(table)
+}
+
+/// Turn the config values into command line arguments, skipping the options
+/// that were given on the command line or through the environment.
+fn config_args(
+    command: &Command,
+    matches: &ArgMatches,
+    values: Table,
+) -> Result<Vec<String>, Error> {
+    let mut args = vec![];
+    for (key, value) in values {
+        let id = key.replace('-', "_");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_id() == id.as_str() && !CLI_ONLY.contains(&id.as_str()))
+            .ok_or_else(|| Error::Config(format!("config: unknown option {}", key)))?;
+        if matches!(
+            matches.value_source(&id),
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        let flag = format!("--{}", arg.get_long().unwrap_or(&id));
+
+        match (arg.get_action(), value) {
+            (ArgAction::SetTrue, Value::Boolean(set)) => {
+                if set {
+                    args.push(flag);
+                }
+            }
+            (ArgAction::SetTrue, _) => {
+                return Err(Error::Config(format!(
+                    "config: {} must be true or false",
+                    key
+                )))
+            }
+            (_, Value::Array(list)) => {
+                for value in list {
+                    args.push(format!("{}={}", flag, scalar(&key, value)?));
+                }
+            }
+            (_, value) => args.push(format!("{}={}", flag, scalar(&key, value)?)),
+        }
+    }
+    Ok(args)
+}
+
+fn scalar(key: &str, value: Value) -> Result<String, Error> {
+    match value {
+        Value::String(value) => Ok(value),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        Value::Boolean(value) => Ok(value.to_string()),
+        _ => Err(Error::Config(format!("config: invalid value for {}", key))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args_for(cli: &[&str], config: &str, profile: Option<&str>) -> Result<Vec<String>, Error> {
+        let command = Args::command();
+        let matches = command.clone().get_matches_from(cli);
+        let values = select_profile(
+            config.parse().unwrap(),
+            profile.map(str::to_string).as_ref(),
+        )?;
+        config_args(&command, &matches, values)
+    }
+
+    #[test]
+    fn converts_values_to_arguments() {
+        let config = r#"
+            location = ["Paris", "Berlin"]
+            date-format = "%m/%d"
+            nerd = true
+            ampm = false
+            interval = 300
+        "#;
+        assert_eq!(
+            args_for(&["wttrbar"], config, None).unwrap(),
+            [
+                "--date-format=%m/%d",
+                "--interval=300",
+                "--location=Paris",
+                "--location=Berlin",
+                "--nerd",
+            ]
+        );
+    }
+
+    #[test]
+    fn command_line_wins() {
+        let config = "location = \"Paris\"\nlang = \"de\"";
+        assert_eq!(
+            args_for(&["wttrbar", "--location", "Berlin"], config, None).unwrap(),
+            ["--lang=de"]
+        );
+    }
+
+    #[test]
+    fn profiles_override_top_level_values() {
+        let config = r#"
+            location = "Paris"
+            [profiles.work]
+            location = "Berlin"
+        "#;
+        assert_eq!(
+            args_for(&["wttrbar"], config, Some("work")).unwrap(),
+            ["--location=Berlin"]
+        );
+        assert!(args_for(&["wttrbar"], config, Some("home")).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert!(args_for(&["wttrbar"], "locaton = \"Paris\"", None).is_err());
+        assert!(args_for(&["wttrbar"], "profile = \"work\"", None).is_err());
+        assert!(args_for(&["wttrbar"], "nerd = \"yes\"", None).is_err());
+    }
+}
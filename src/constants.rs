@@ -148,6 +148,18 @@ pub const WEATHER_CODES_NERD: &[(i32, &str)] = &[
     (431, "󰖗"),
 ];
 
+/// Night variants of the clear and partly cloudy codes, used instead of the
+/// tables above between sunset and sunrise.
+pub const WEATHER_CODES_NIGHT: &[(i32, &str)] = &[
+    (113, "🌙"),   // Clear
+    (116, "🌙☁️"), // Partly cloudly, the moon behind a cloud
+];
+
+pub const WEATHER_CODES_NIGHT_NERD: &[(i32, &str)] = &[
+    (113, "󰖔"), // Clear
+    (116, "󰼱"), // Partly cloudly, nf-md-weather_night_partly_cloudy
+];
+
 pub const MOON_PHASES: &[(&str, &str)] = &[
     ("New Moon", "🌑"),
     ("Waxing Crescent", "🌒"),
@@ -170,4 +182,692 @@ pub const MOON_PHASES_NERD: &[(&str, &str)] = &[
     ("Waning Crescent", "󰽥"),
 ];
 
-pub const ICON_PLACEHOLDER: &str = "{ICON}";
+/// Language independent CSS classes for the weather codes: the condition and
+/// the family it belongs to.
+pub const WEATHER_CLASSES: &[(i32, &str, &str)] = &[
+    (113, "sunny", "clear"),
+    (116, "partly-cloudy", "cloudy"),
+    (119, "cloudy", "cloudy"),
+    (122, "overcast", "cloudy"),
+    (125, "haze", "fog"),
+    (128, "haze", "fog"),
+    (131, "dust", "dust"),
+    (134, "dust", "dust"),
+    (137, "dust", "dust"),
+    (140, "dust", "dust"),
+    (143, "fog", "fog"),
+    (146, "haze", "fog"),
+    (149, "haze", "fog"),
+    (152, "haze", "fog"),
+    (155, "haze", "fog"),
+    (158, "haze", "fog"),
+    (161, "haze", "fog"),
+    (176, "rain-light", "rain"),
+    (179, "sleet", "sleet"),
+    (182, "sleet", "sleet"),
+    (185, "sleet", "sleet"),
+    (200, "thunder", "thunder"),
+    (227, "snow-light", "snow"),
+    (230, "snow-heavy", "snow"),
+    (248, "fog", "fog"),
+    (260, "fog", "fog"),
+    (263, "rain-light", "rain"),
+    (266, "rain-light", "rain"),
+    (281, "sleet", "sleet"),
+    (284, "sleet", "sleet"),
+    (293, "rain-light", "rain"),
+    (296, "rain-light", "rain"),
+    (299, "rain-moderate", "rain"),
+    (302, "rain-moderate", "rain"),
+    (305, "rain-heavy", "rain"),
+    (308, "rain-heavy", "rain"),
+    (311, "sleet", "sleet"),
+    (314, "sleet", "sleet"),
+    (317, "sleet", "sleet"),
+    (320, "snow-light", "snow"),
+    (323, "snow-light", "snow"),
+    (326, "snow-light", "snow"),
+    (329, "snow-moderate", "snow"),
+    (332, "snow-moderate", "snow"),
+    (335, "snow-heavy", "snow"),
+    (338, "snow-heavy", "snow"),
+    (350, "sleet", "sleet"),
+    (353, "rain-light", "rain"),
+    (356, "rain-moderate", "rain"),
+    (359, "rain-heavy", "rain"),
+    (362, "sleet", "sleet"),
+    (365, "sleet", "sleet"),
+    (368, "snow-light", "snow"),
+    (371, "snow-heavy", "snow"),
+    (374, "sleet", "sleet"),
+    (377, "sleet", "sleet"),
+    (386, "thunder", "thunder"),
+    (389, "thunder", "thunder"),
+    (392, "thunder", "thunder"),
+    (395, "snow-heavy", "snow"),
+    // The codes after 395 that wttr.in defines, shown as light rain above.
+    (398, "rain-light", "rain"),
+    (401, "rain-light", "rain"),
+    (404, "rain-light", "rain"),
+    (407, "rain-light", "rain"),
+    (410, "rain-light", "rain"),
+    (413, "rain-light", "rain"),
+    (416, "rain-light", "rain"),
+    (419, "rain-light", "rain"),
+    (422, "rain-light", "rain"),
+    (425, "rain-light", "rain"),
+    (428, "rain-light", "rain"),
+    (431, "rain-light", "rain"),
+];
+
+/// Temperature band classes with the temperature (°C) each one stays below.
+pub const TEMPERATURE_BANDS: &[(i32, &str)] = &[
+    (0, "temp-freezing"),
+    (10, "temp-cold"),
+    (20, "temp-mild"),
+    (28, "temp-warm"),
+    (i32::MAX, "temp-hot"),
+];
+
+/// Put in front of the bar text while an `--alert` rule matches.
+pub const ALERT_ICON: &str = "⚠️";
+
+pub const ALERT_ICON_NERD: &str = "󰀪";
+
+/// Arrows for the direction the wind blows to, starting with wind from the
+/// north and going clockwise in steps of 45°.
+pub const WIND_DIRECTIONS: &[&str] = &["⬇️", "↙️", "⬅️", "↖️", "⬆️", "↗️", "➡️", "↘️"];
+
+pub const WIND_DIRECTIONS_NERD: &[&str] = &["󰁅", "󰁂", "󰁍", "󰁛", "󰁝", "󰁜", "󰁔", "󰁃"];
+
+/// WMO weather interpretation codes (as used by Open-Meteo) mapped onto the
+/// closest wttr.in code, so the icon tables above can be reused.
+pub const WMO_CODES: &[(i32, i32, &str)] = &[
+    (0, 113, "Clear sky"),
+    (1, 116, "Mainly clear"),
+    (2, 116, "Partly cloudy"),
+    (3, 122, "Overcast"),
+    (45, 248, "Fog"),
+    (48, 260, "Depositing rime fog"),
+    (51, 266, "Light drizzle"),
+    (53, 266, "Moderate drizzle"),
+    (55, 266, "Dense drizzle"),
+    (56, 311, "Light freezing drizzle"),
+    (57, 314, "Dense freezing drizzle"),
+    (61, 296, "Slight rain"),
+    (63, 302, "Moderate rain"),
+    (65, 308, "Heavy rain"),
+    (66, 311, "Light freezing rain"),
+    (67, 314, "Heavy freezing rain"),
+    (71, 326, "Slight snow fall"),
+    (73, 332, "Moderate snow fall"),
+    (75, 338, "Heavy snow fall"),
+    (77, 320, "Snow grains"),
+    (80, 353, "Slight rain showers"),
+    (81, 356, "Moderate rain showers"),
+    (82, 359, "Violent rain showers"),
+    (85, 368, "Slight snow showers"),
+    (86, 371, "Heavy snow showers"),
+    (95, 386, "Thunderstorm"),
+    (96, 389, "Thunderstorm with slight hail"),
+    (99, 389, "Thunderstorm with heavy hail"),
+];
+
+/// `current_condition` keys that SIGUSR2 cycles the bar text through in daemon
+/// mode, after `--custom-indicator` and `--main-indicator`.
+pub const INDICATOR_PRESETS: &[&str] = &["temp_C", "FeelsLikeC", "humidity", "windspeedKmph"];
+
+/// The coordinates of the main city of every time zone, from tzdata's
+/// `zone.tab`, for `--location auto-tz`.
+pub const TIME_ZONE_COORDINATES: &[(&str, f64, f64)] = &[
+    ("Africa/Abidjan", 5.32, -4.03),
+    ("Africa/Accra", 5.55, -0.22),
+    ("Africa/Addis_Ababa", 9.03, 38.70),
+    ("Africa/Algiers", 36.78, 3.05),
+    ("Africa/Asmara", 15.33, 38.88),
+    ("Africa/Bamako", 12.65, -8.00),
+    ("Africa/Bangui", 4.37, 18.58),
+    ("Africa/Banjul", 13.47, -16.65),
+    ("Africa/Bissau", 11.85, -15.58),
+    ("Africa/Blantyre", -15.78, 35.00),
+    ("Africa/Brazzaville", -4.27, 15.28),
+    ("Africa/Bujumbura", -3.38, 29.37),
+    ("Africa/Cairo", 30.05, 31.25),
+    ("Africa/Casablanca", 33.65, -7.58),
+    ("Africa/Ceuta", 35.88, -5.32),
+    ("Africa/Conakry", 9.52, -13.72),
+    ("Africa/Dakar", 14.67, -17.43),
+    ("Africa/Dar_es_Salaam", -6.80, 39.28),
+    ("Africa/Djibouti", 11.60, 43.15),
+    ("Africa/Douala", 4.05, 9.70),
+    ("Africa/El_Aaiun", 27.15, -13.20),
+    ("Africa/Freetown", 8.50, -13.25),
+    ("Africa/Gaborone", -24.65, 25.92),
+    ("Africa/Harare", -17.83, 31.05),
+    ("Africa/Johannesburg", -26.25, 28.00),
+    ("Africa/Juba", 4.85, 31.62),
+    ("Africa/Kampala", 0.32, 32.42),
+    ("Africa/Khartoum", 15.60, 32.53),
+    ("Africa/Kigali", -1.95, 30.07),
+    ("Africa/Kinshasa", -4.30, 15.30),
+    ("Africa/Lagos", 6.45, 3.40),
+    ("Africa/Libreville", 0.38, 9.45),
+    ("Africa/Lome", 6.13, 1.22),
+    ("Africa/Luanda", -8.80, 13.23),
+    ("Africa/Lubumbashi", -11.67, 27.47),
+    ("Africa/Lusaka", -15.42, 28.28),
+    ("Africa/Malabo", 3.75, 8.78),
+    ("Africa/Maputo", -25.97, 32.58),
+    ("Africa/Maseru", -29.47, 27.50),
+    ("Africa/Mbabane", -26.30, 31.10),
+    ("Africa/Mogadishu", 2.07, 45.37),
+    ("Africa/Monrovia", 6.30, -10.78),
+    ("Africa/Nairobi", -1.28, 36.82),
+    ("Africa/Ndjamena", 12.12, 15.05),
+    ("Africa/Niamey", 13.52, 2.12),
+    ("Africa/Nouakchott", 18.10, -15.95),
+    ("Africa/Ouagadougou", 12.37, -1.52),
+    ("Africa/Porto-Novo", 6.48, 2.62),
+    ("Africa/Sao_Tome", 0.33, 6.73),
+    ("Africa/Tripoli", 32.90, 13.18),
+    ("Africa/Tunis", 36.80, 10.18),
+    ("Africa/Windhoek", -22.57, 17.10),
+    ("America/Adak", 51.88, -176.66),
+    ("America/Anchorage", 61.22, -149.90),
+    ("America/Anguilla", 18.20, -63.07),
+    ("America/Antigua", 17.05, -61.80),
+    ("America/Araguaina", -7.20, -48.20),
+    ("America/Argentina/Buenos_Aires", -34.60, -58.45),
+    ("America/Argentina/Catamarca", -28.47, -65.78),
+    ("America/Argentina/Cordoba", -31.40, -64.18),
+    ("America/Argentina/Jujuy", -24.18, -65.30),
+    ("America/Argentina/La_Rioja", -29.43, -66.85),
+    ("America/Argentina/Mendoza", -32.88, -68.82),
+    ("America/Argentina/Rio_Gallegos", -51.63, -69.22),
+    ("America/Argentina/Salta", -24.78, -65.42),
+    ("America/Argentina/San_Juan", -31.53, -68.52),
+    ("America/Argentina/San_Luis", -33.32, -66.35),
+    ("America/Argentina/Tucuman", -26.82, -65.22),
+    ("America/Argentina/Ushuaia", -54.80, -68.30),
+    ("America/Aruba", 12.50, -69.97),
+    ("America/Asuncion", -25.27, -57.67),
+    ("America/Atikokan", 48.76, -91.62),
+    ("America/Bahia", -12.98, -38.52),
+    ("America/Bahia_Banderas", 20.80, -105.25),
+    ("America/Barbados", 13.10, -59.62),
+    ("America/Belem", -1.45, -48.48),
+    ("America/Belize", 17.50, -88.20),
+    ("America/Blanc-Sablon", 51.42, -57.12),
+    ("America/Boa_Vista", 2.82, -60.67),
+    ("America/Bogota", 4.60, -74.08),
+    ("America/Boise", 43.61, -116.20),
+    ("America/Cambridge_Bay", 69.11, -105.05),
+    ("America/Campo_Grande", -20.45, -54.62),
+    ("America/Cancun", 21.08, -86.77),
+    ("America/Caracas", 10.50, -66.93),
+    ("America/Cayenne", 4.93, -52.33),
+    ("America/Cayman", 19.30, -81.38),
+    ("America/Chicago", 41.85, -87.65),
+    ("America/Chihuahua", 28.63, -106.08),
+    ("America/Ciudad_Juarez", 31.73, -106.48),
+    ("America/Costa_Rica", 9.93, -84.08),
+    ("America/Coyhaique", -45.57, -72.07),
+    ("America/Creston", 49.10, -116.52),
+    ("America/Cuiaba", -15.58, -56.08),
+    ("America/Curacao", 12.18, -69.00),
+    ("America/Danmarkshavn", 76.77, -18.67),
+    ("America/Dawson", 64.07, -139.42),
+    ("America/Dawson_Creek", 55.77, -120.23),
+    ("America/Denver", 39.74, -104.98),
+    ("America/Detroit", 42.33, -83.05),
+    ("America/Dominica", 15.30, -61.40),
+    ("America/Edmonton", 53.55, -113.47),
+    ("America/Eirunepe", -6.67, -69.87),
+    ("America/El_Salvador", 13.70, -89.20),
+    ("America/Fort_Nelson", 58.80, -122.70),
+    ("America/Fortaleza", -3.72, -38.50),
+    ("America/Glace_Bay", 46.20, -59.95),
+    ("America/Goose_Bay", 53.33, -60.42),
+    ("America/Grand_Turk", 21.47, -71.13),
+    ("America/Grenada", 12.05, -61.75),
+    ("America/Guadeloupe", 16.23, -61.53),
+    ("America/Guatemala", 14.63, -90.52),
+    ("America/Guayaquil", -2.17, -79.83),
+    ("America/Guyana", 6.80, -58.17),
+    ("America/Halifax", 44.65, -63.60),
+    ("America/Havana", 23.13, -82.37),
+    ("America/Hermosillo", 29.07, -110.97),
+    ("America/Indiana/Indianapolis", 39.77, -86.16),
+    ("America/Indiana/Knox", 41.30, -86.62),
+    ("America/Indiana/Marengo", 38.38, -86.34),
+    ("America/Indiana/Petersburg", 38.49, -87.28),
+    ("America/Indiana/Tell_City", 37.95, -86.76),
+    ("America/Indiana/Vevay", 38.75, -85.07),
+    ("America/Indiana/Vincennes", 38.68, -87.53),
+    ("America/Indiana/Winamac", 41.05, -86.60),
+    ("America/Inuvik", 68.35, -133.72),
+    ("America/Iqaluit", 63.73, -68.47),
+    ("America/Jamaica", 17.97, -76.79),
+    ("America/Juneau", 58.30, -134.42),
+    ("America/Kentucky/Louisville", 38.25, -85.76),
+    ("America/Kentucky/Monticello", 36.83, -84.85),
+    ("America/Kralendijk", 12.15, -68.28),
+    ("America/La_Paz", -16.50, -68.15),
+    ("America/Lima", -12.05, -77.05),
+    ("America/Los_Angeles", 34.05, -118.24),
+    ("America/Lower_Princes", 18.05, -63.05),
+    ("America/Maceio", -9.67, -35.72),
+    ("America/Managua", 12.15, -86.28),
+    ("America/Manaus", -3.13, -60.02),
+    ("America/Marigot", 18.07, -63.08),
+    ("America/Martinique", 14.60, -61.08),
+    ("America/Matamoros", 25.83, -97.50),
+    ("America/Mazatlan", 23.22, -106.42),
+    ("America/Menominee", 45.11, -87.61),
+    ("America/Merida", 20.97, -89.62),
+    ("America/Metlakatla", 55.13, -131.58),
+    ("America/Mexico_City", 19.40, -99.15),
+    ("America/Miquelon", 47.05, -56.33),
+    ("America/Moncton", 46.10, -64.78),
+    ("America/Monterrey", 25.67, -100.32),
+    ("America/Montevideo", -34.91, -56.21),
+    ("America/Montserrat", 16.72, -62.22),
+    ("America/Nassau", 25.08, -77.35),
+    ("America/New_York", 40.71, -74.01),
+    ("America/Nome", 64.50, -165.41),
+    ("America/Noronha", -3.85, -32.42),
+    ("America/North_Dakota/Beulah", 47.26, -101.78),
+    ("America/North_Dakota/Center", 47.12, -101.30),
+    ("America/North_Dakota/New_Salem", 46.84, -101.41),
+    ("America/Nuuk", 64.18, -51.73),
+    ("America/Ojinaga", 29.57, -104.42),
+    ("America/Panama", 8.97, -79.53),
+    ("America/Paramaribo", 5.83, -55.17),
+    ("America/Phoenix", 33.45, -112.07),
+    ("America/Port-au-Prince", 18.53, -72.33),
+    ("America/Port_of_Spain", 10.65, -61.52),
+    ("America/Porto_Velho", -8.77, -63.90),
+    ("America/Puerto_Rico", 18.47, -66.11),
+    ("America/Punta_Arenas", -53.15, -70.92),
+    ("America/Rankin_Inlet", 62.82, -92.08),
+    ("America/Recife", -8.05, -34.90),
+    ("America/Regina", 50.40, -104.65),
+    ("America/Resolute", 74.70, -94.83),
+    ("America/Rio_Branco", -9.97, -67.80),
+    ("America/Santarem", -2.43, -54.87),
+    ("America/Santiago", -33.45, -70.67),
+    ("America/Santo_Domingo", 18.47, -69.90),
+    ("America/Sao_Paulo", -23.53, -46.62),
+    ("America/Scoresbysund", 70.48, -21.97),
+    ("America/Sitka", 57.18, -135.30),
+    ("America/St_Barthelemy", 17.88, -62.85),
+    ("America/St_Johns", 47.57, -52.72),
+    ("America/St_Kitts", 17.30, -62.72),
+    ("America/St_Lucia", 14.02, -61.00),
+    ("America/St_Thomas", 18.35, -64.93),
+    ("America/St_Vincent", 13.15, -61.23),
+    ("America/Swift_Current", 50.28, -107.83),
+    ("America/Tegucigalpa", 14.10, -87.22),
+    ("America/Thule", 76.57, -68.78),
+    ("America/Tijuana", 32.53, -117.02),
+    ("America/Toronto", 43.65, -79.38),
+    ("America/Tortola", 18.45, -64.62),
+    ("America/Vancouver", 49.27, -123.12),
+    ("America/Whitehorse", 60.72, -135.05),
+    ("America/Winnipeg", 49.88, -97.15),
+    ("America/Yakutat", 59.55, -139.73),
+    ("Antarctica/Casey", -66.28, 110.52),
+    ("Antarctica/Davis", -68.58, 77.97),
+    ("Antarctica/DumontDUrville", -66.67, 140.02),
+    ("Antarctica/Macquarie", -54.50, 158.95),
+    ("Antarctica/Mawson", -67.60, 62.88),
+    ("Antarctica/McMurdo", -77.83, 166.60),
+    ("Antarctica/Palmer", -64.80, -64.10),
+    ("Antarctica/Rothera", -67.57, -68.13),
+    ("Antarctica/Syowa", -69.01, 39.59),
+    ("Antarctica/Troll", -72.01, 2.53),
+    ("Antarctica/Vostok", -78.40, 106.90),
+    ("Arctic/Longyearbyen", 78.00, 16.00),
+    ("Asia/Aden", 12.75, 45.20),
+    ("Asia/Almaty", 43.25, 76.95),
+    ("Asia/Amman", 31.95, 35.93),
+    ("Asia/Anadyr", 64.75, 177.48),
+    ("Asia/Aqtau", 44.52, 50.27),
+    ("Asia/Aqtobe", 50.28, 57.17),
+    ("Asia/Ashgabat", 37.95, 58.38),
+    ("Asia/Atyrau", 47.12, 51.93),
+    ("Asia/Baghdad", 33.35, 44.42),
+    ("Asia/Bahrain", 26.38, 50.58),
+    ("Asia/Baku", 40.38, 49.85),
+    ("Asia/Bangkok", 13.75, 100.52),
+    ("Asia/Barnaul", 53.37, 83.75),
+    ("Asia/Beirut", 33.88, 35.50),
+    ("Asia/Bishkek", 42.90, 74.60),
+    ("Asia/Brunei", 4.93, 114.92),
+    ("Asia/Chita", 52.05, 113.47),
+    ("Asia/Colombo", 6.93, 79.85),
+    ("Asia/Damascus", 33.50, 36.30),
+    ("Asia/Dhaka", 23.72, 90.42),
+    ("Asia/Dili", -8.55, 125.58),
+    ("Asia/Dubai", 25.30, 55.30),
+    ("Asia/Dushanbe", 38.58, 68.80),
+    ("Asia/Famagusta", 35.12, 33.95),
+    ("Asia/Gaza", 31.50, 34.47),
+    ("Asia/Hebron", 31.53, 35.09),
+    ("Asia/Ho_Chi_Minh", 10.75, 106.67),
+    ("Asia/Hong_Kong", 22.28, 114.15),
+    ("Asia/Hovd", 48.02, 91.65),
+    ("Asia/Irkutsk", 52.27, 104.33),
+    ("Asia/Jakarta", -6.17, 106.80),
+    ("Asia/Jayapura", -2.53, 140.70),
+    ("Asia/Jerusalem", 31.78, 35.22),
+    ("Asia/Kabul", 34.52, 69.20),
+    ("Asia/Kamchatka", 53.02, 158.65),
+    ("Asia/Karachi", 24.87, 67.05),
+    ("Asia/Kathmandu", 27.72, 85.32),
+    ("Asia/Khandyga", 62.66, 135.55),
+    ("Asia/Kolkata", 22.53, 88.37),
+    ("Asia/Krasnoyarsk", 56.02, 92.83),
+    ("Asia/Kuala_Lumpur", 3.17, 101.70),
+    ("Asia/Kuching", 1.55, 110.33),
+    ("Asia/Kuwait", 29.33, 47.98),
+    ("Asia/Macau", 22.20, 113.54),
+    ("Asia/Magadan", 59.57, 150.80),
+    ("Asia/Makassar", -5.12, 119.40),
+    ("Asia/Manila", 14.59, 120.97),
+    ("Asia/Muscat", 23.60, 58.58),
+    ("Asia/Nicosia", 35.17, 33.37),
+    ("Asia/Novokuznetsk", 53.75, 87.12),
+    ("Asia/Novosibirsk", 55.03, 82.92),
+    ("Asia/Omsk", 55.00, 73.40),
+    ("Asia/Oral", 51.22, 51.35),
+    ("Asia/Phnom_Penh", 11.55, 104.92),
+    ("Asia/Pontianak", -0.03, 109.33),
+    ("Asia/Pyongyang", 39.02, 125.75),
+    ("Asia/Qatar", 25.28, 51.53),
+    ("Asia/Qostanay", 53.20, 63.62),
+    ("Asia/Qyzylorda", 44.80, 65.47),
+    ("Asia/Riyadh", 24.63, 46.72),
+    ("Asia/Sakhalin", 46.97, 142.70),
+    ("Asia/Samarkand", 39.67, 66.80),
+    ("Asia/Seoul", 37.55, 126.97),
+    ("Asia/Shanghai", 31.23, 121.47),
+    ("Asia/Singapore", 1.28, 103.85),
+    ("Asia/Srednekolymsk", 67.47, 153.72),
+    ("Asia/Taipei", 25.05, 121.50),
+    ("Asia/Tashkent", 41.33, 69.30),
+    ("Asia/Tbilisi", 41.72, 44.82),
+    ("Asia/Tehran", 35.67, 51.43),
+    ("Asia/Thimphu", 27.47, 89.65),
+    ("Asia/Tokyo", 35.65, 139.74),
+    ("Asia/Tomsk", 56.50, 84.97),
+    ("Asia/Ulaanbaatar", 47.92, 106.88),
+    ("Asia/Urumqi", 43.80, 87.58),
+    ("Asia/Ust-Nera", 64.56, 143.23),
+    ("Asia/Vientiane", 17.97, 102.60),
+    ("Asia/Vladivostok", 43.17, 131.93),
+    ("Asia/Yakutsk", 62.00, 129.67),
+    ("Asia/Yangon", 16.78, 96.17),
+    ("Asia/Yekaterinburg", 56.85, 60.60),
+    ("Asia/Yerevan", 40.18, 44.50),
+    ("Atlantic/Azores", 37.73, -25.67),
+    ("Atlantic/Bermuda", 32.28, -64.77),
+    ("Atlantic/Canary", 28.10, -15.40),
+    ("Atlantic/Cape_Verde", 14.92, -23.52),
+    ("Atlantic/Faroe", 62.02, -6.77),
+    ("Atlantic/Madeira", 32.63, -16.90),
+    ("Atlantic/Reykjavik", 64.15, -21.85),
+    ("Atlantic/South_Georgia", -54.27, -36.53),
+    ("Atlantic/St_Helena", -15.92, -5.70),
+    ("Atlantic/Stanley", -51.70, -57.85),
+    ("Australia/Adelaide", -34.92, 138.58),
+    ("Australia/Brisbane", -27.47, 153.03),
+    ("Australia/Broken_Hill", -31.95, 141.45),
+    ("Australia/Darwin", -12.47, 130.83),
+    ("Australia/Eucla", -31.72, 128.87),
+    ("Australia/Hobart", -42.88, 147.32),
+    ("Australia/Lindeman", -20.27, 149.00),
+    ("Australia/Lord_Howe", -31.55, 159.08),
+    ("Australia/Melbourne", -37.82, 144.97),
+    ("Australia/Perth", -31.95, 115.85),
+    ("Australia/Sydney", -33.87, 151.22),
+    ("Europe/Amsterdam", 52.37, 4.90),
+    ("Europe/Andorra", 42.50, 1.52),
+    ("Europe/Astrakhan", 46.35, 48.05),
+    ("Europe/Athens", 37.97, 23.72),
+    ("Europe/Belgrade", 44.83, 20.50),
+    ("Europe/Berlin", 52.50, 13.37),
+    ("Europe/Bratislava", 48.15, 17.12),
+    ("Europe/Brussels", 50.83, 4.33),
+    ("Europe/Bucharest", 44.43, 26.10),
+    ("Europe/Budapest", 47.50, 19.08),
+    ("Europe/Busingen", 47.70, 8.68),
+    ("Europe/Chisinau", 47.00, 28.83),
+    ("Europe/Copenhagen", 55.67, 12.58),
+    ("Europe/Dublin", 53.33, -6.25),
+    ("Europe/Gibraltar", 36.13, -5.35),
+    ("Europe/Guernsey", 49.45, -2.54),
+    ("Europe/Helsinki", 60.17, 24.97),
+    ("Europe/Isle_of_Man", 54.15, -4.47),
+    ("Europe/Istanbul", 41.02, 28.97),
+    ("Europe/Jersey", 49.18, -2.11),
+    ("Europe/Kaliningrad", 54.72, 20.50),
+    ("Europe/Kirov", 58.60, 49.65),
+    ("Europe/Kyiv", 50.43, 30.52),
+    ("Europe/Lisbon", 38.72, -9.13),
+    ("Europe/Ljubljana", 46.05, 14.52),
+    ("Europe/London", 51.51, -0.13),
+    ("Europe/Luxembourg", 49.60, 6.15),
+    ("Europe/Madrid", 40.40, -3.68),
+    ("Europe/Malta", 35.90, 14.52),
+    ("Europe/Mariehamn", 60.10, 19.95),
+    ("Europe/Minsk", 53.90, 27.57),
+    ("Europe/Monaco", 43.70, 7.38),
+    ("Europe/Moscow", 55.76, 37.62),
+    ("Europe/Oslo", 59.92, 10.75),
+    ("Europe/Paris", 48.87, 2.33),
+    ("Europe/Podgorica", 42.43, 19.27),
+    ("Europe/Prague", 50.08, 14.43),
+    ("Europe/Riga", 56.95, 24.10),
+    ("Europe/Rome", 41.90, 12.48),
+    ("Europe/Samara", 53.20, 50.15),
+    ("Europe/San_Marino", 43.92, 12.47),
+    ("Europe/Sarajevo", 43.87, 18.42),
+    ("Europe/Saratov", 51.57, 46.03),
+    ("Europe/Simferopol", 44.95, 34.10),
+    ("Europe/Skopje", 41.98, 21.43),
+    ("Europe/Sofia", 42.68, 23.32),
+    ("Europe/Stockholm", 59.33, 18.05),
+    ("Europe/Tallinn", 59.42, 24.75),
+    ("Europe/Tirane", 41.33, 19.83),
+    ("Europe/Ulyanovsk", 54.33, 48.40),
+    ("Europe/Vaduz", 47.15, 9.52),
+    ("Europe/Vatican", 41.90, 12.45),
+    ("Europe/Vienna", 48.22, 16.33),
+    ("Europe/Vilnius", 54.68, 25.32),
+    ("Europe/Volgograd", 48.73, 44.42),
+    ("Europe/Warsaw", 52.25, 21.00),
+    ("Europe/Zagreb", 45.80, 15.97),
+    ("Europe/Zurich", 47.38, 8.53),
+    ("Indian/Antananarivo", -18.92, 47.52),
+    ("Indian/Chagos", -7.33, 72.42),
+    ("Indian/Christmas", -10.42, 105.72),
+    ("Indian/Cocos", -12.17, 96.92),
+    ("Indian/Comoro", -11.68, 43.27),
+    ("Indian/Kerguelen", -49.35, 70.22),
+    ("Indian/Mahe", -4.67, 55.47),
+    ("Indian/Maldives", 4.17, 73.50),
+    ("Indian/Mauritius", -20.17, 57.50),
+    ("Indian/Mayotte", -12.78, 45.23),
+    ("Indian/Reunion", -20.87, 55.47),
+    ("Pacific/Apia", -13.83, -171.73),
+    ("Pacific/Auckland", -36.87, 174.77),
+    ("Pacific/Bougainville", -6.22, 155.57),
+    ("Pacific/Chatham", -43.95, -176.55),
+    ("Pacific/Chuuk", 7.42, 151.78),
+    ("Pacific/Easter", -27.15, -109.43),
+    ("Pacific/Efate", -17.67, 168.42),
+    ("Pacific/Fakaofo", -9.37, -171.23),
+    ("Pacific/Fiji", -18.13, 178.42),
+    ("Pacific/Funafuti", -8.52, 179.22),
+    ("Pacific/Galapagos", -0.90, -89.60),
+    ("Pacific/Gambier", -23.13, -134.95),
+    ("Pacific/Guadalcanal", -9.53, 160.20),
+    ("Pacific/Guam", 13.47, 144.75),
+    ("Pacific/Honolulu", 21.31, -157.86),
+    ("Pacific/Kanton", -2.78, -171.72),
+    ("Pacific/Kiritimati", 1.87, -157.33),
+    ("Pacific/Kosrae", 5.32, 162.98),
+    ("Pacific/Kwajalein", 9.08, 167.33),
+    ("Pacific/Majuro", 7.15, 171.20),
+    ("Pacific/Marquesas", -9.00, -139.50),
+    ("Pacific/Midway", 28.22, -177.37),
+    ("Pacific/Nauru", -0.52, 166.92),
+    ("Pacific/Niue", -19.02, -169.92),
+    ("Pacific/Norfolk", -29.05, 167.97),
+    ("Pacific/Noumea", -22.27, 166.45),
+    ("Pacific/Pago_Pago", -14.27, -170.70),
+    ("Pacific/Palau", 7.33, 134.48),
+    ("Pacific/Pitcairn", -25.07, -130.08),
+    ("Pacific/Pohnpei", 6.97, 158.22),
+    ("Pacific/Port_Moresby", -9.50, 147.17),
+    ("Pacific/Rarotonga", -21.23, -159.77),
+    ("Pacific/Saipan", 15.20, 145.75),
+    ("Pacific/Tahiti", -17.53, -149.57),
+    ("Pacific/Tarawa", 1.42, 173.00),
+    ("Pacific/Tongatapu", -21.13, -175.20),
+    ("Pacific/Wake", 19.28, 166.62),
+    ("Pacific/Wallis", -13.30, -176.17),
+];
+
+/// Old and alternative zone names, like `US/Eastern`, with the zone in
+/// [`TIME_ZONE_COORDINATES`] they link to, from tzdata's `backward` file.
+pub const TIME_ZONE_LINKS: &[(&str, &str)] = &[
+    ("Africa/Asmera", "Africa/Nairobi"),
+    ("Africa/Timbuktu", "Africa/Abidjan"),
+    (
+        "America/Argentina/ComodRivadavia",
+        "America/Argentina/Catamarca",
+    ),
+    ("America/Atka", "America/Adak"),
+    ("America/Buenos_Aires", "America/Argentina/Buenos_Aires"),
+    ("America/Catamarca", "America/Argentina/Catamarca"),
+    ("America/Coral_Harbour", "America/Panama"),
+    ("America/Cordoba", "America/Argentina/Cordoba"),
+    ("America/Ensenada", "America/Tijuana"),
+    ("America/Fort_Wayne", "America/Indiana/Indianapolis"),
+    ("America/Godthab", "America/Nuuk"),
+    ("America/Indianapolis", "America/Indiana/Indianapolis"),
+    ("America/Jujuy", "America/Argentina/Jujuy"),
+    ("America/Knox_IN", "America/Indiana/Knox"),
+    ("America/Louisville", "America/Kentucky/Louisville"),
+    ("America/Mendoza", "America/Argentina/Mendoza"),
+    ("America/Montreal", "America/Toronto"),
+    ("America/Nipigon", "America/Toronto"),
+    ("America/Pangnirtung", "America/Iqaluit"),
+    ("America/Porto_Acre", "America/Rio_Branco"),
+    ("America/Rainy_River", "America/Winnipeg"),
+    ("America/Rosario", "America/Argentina/Cordoba"),
+    ("America/Santa_Isabel", "America/Tijuana"),
+    ("America/Shiprock", "America/Denver"),
+    ("America/Thunder_Bay", "America/Toronto"),
+    ("America/Virgin", "America/Puerto_Rico"),
+    ("America/Yellowknife", "America/Edmonton"),
+    ("Antarctica/South_Pole", "Pacific/Auckland"),
+    ("Asia/Ashkhabad", "Asia/Ashgabat"),
+    ("Asia/Calcutta", "Asia/Kolkata"),
+    ("Asia/Choibalsan", "Asia/Ulaanbaatar"),
+    ("Asia/Chongqing", "Asia/Shanghai"),
+    ("Asia/Chungking", "Asia/Shanghai"),
+    ("Asia/Dacca", "Asia/Dhaka"),
+    ("Asia/Harbin", "Asia/Shanghai"),
+    ("Asia/Istanbul", "Europe/Istanbul"),
+    ("Asia/Kashgar", "Asia/Urumqi"),
+    ("Asia/Katmandu", "Asia/Kathmandu"),
+    ("Asia/Macao", "Asia/Macau"),
+    ("Asia/Rangoon", "Asia/Yangon"),
+    ("Asia/Saigon", "Asia/Ho_Chi_Minh"),
+    ("Asia/Tel_Aviv", "Asia/Jerusalem"),
+    ("Asia/Thimbu", "Asia/Thimphu"),
+    ("Asia/Ujung_Pandang", "Asia/Makassar"),
+    ("Asia/Ulan_Bator", "Asia/Ulaanbaatar"),
+    ("Atlantic/Faeroe", "Atlantic/Faroe"),
+    ("Atlantic/Jan_Mayen", "Europe/Berlin"),
+    ("Australia/ACT", "Australia/Sydney"),
+    ("Australia/Canberra", "Australia/Sydney"),
+    ("Australia/Currie", "Australia/Hobart"),
+    ("Australia/LHI", "Australia/Lord_Howe"),
+    ("Australia/NSW", "Australia/Sydney"),
+    ("Australia/North", "Australia/Darwin"),
+    ("Australia/Queensland", "Australia/Brisbane"),
+    ("Australia/South", "Australia/Adelaide"),
+    ("Australia/Tasmania", "Australia/Hobart"),
+    ("Australia/Victoria", "Australia/Melbourne"),
+    ("Australia/West", "Australia/Perth"),
+    ("Australia/Yancowinna", "Australia/Broken_Hill"),
+    ("Brazil/Acre", "America/Rio_Branco"),
+    ("Brazil/DeNoronha", "America/Noronha"),
+    ("Brazil/East", "America/Sao_Paulo"),
+    ("Brazil/West", "America/Manaus"),
+    ("Canada/Atlantic", "America/Halifax"),
+    ("Canada/Central", "America/Winnipeg"),
+    ("Canada/Eastern", "America/Toronto"),
+    ("Canada/Mountain", "America/Edmonton"),
+    ("Canada/Newfoundland", "America/St_Johns"),
+    ("Canada/Pacific", "America/Vancouver"),
+    ("Canada/Saskatchewan", "America/Regina"),
+    ("Canada/Yukon", "America/Whitehorse"),
+    ("Chile/Continental", "America/Santiago"),
+    ("Chile/EasterIsland", "Pacific/Easter"),
+    ("Cuba", "America/Havana"),
+    ("Egypt", "Africa/Cairo"),
+    ("Eire", "Europe/Dublin"),
+    ("Europe/Belfast", "Europe/London"),
+    ("Europe/Kiev", "Europe/Kyiv"),
+    ("Europe/Nicosia", "Asia/Nicosia"),
+    ("Europe/Tiraspol", "Europe/Chisinau"),
+    ("Europe/Uzhgorod", "Europe/Kyiv"),
+    ("Europe/Zaporozhye", "Europe/Kyiv"),
+    ("GB", "Europe/London"),
+    ("GB-Eire", "Europe/London"),
+    ("Hongkong", "Asia/Hong_Kong"),
+    ("Iceland", "Africa/Abidjan"),
+    ("Iran", "Asia/Tehran"),
+    ("Israel", "Asia/Jerusalem"),
+    ("Jamaica", "America/Jamaica"),
+    ("Japan", "Asia/Tokyo"),
+    ("Kwajalein", "Pacific/Kwajalein"),
+    ("Libya", "Africa/Tripoli"),
+    ("Mexico/BajaNorte", "America/Tijuana"),
+    ("Mexico/BajaSur", "America/Mazatlan"),
+    ("Mexico/General", "America/Mexico_City"),
+    ("NZ", "Pacific/Auckland"),
+    ("NZ-CHAT", "Pacific/Chatham"),
+    ("Navajo", "America/Denver"),
+    ("PRC", "Asia/Shanghai"),
+    ("Pacific/Enderbury", "Pacific/Kanton"),
+    ("Pacific/Johnston", "Pacific/Honolulu"),
+    ("Pacific/Ponape", "Pacific/Guadalcanal"),
+    ("Pacific/Samoa", "Pacific/Pago_Pago"),
+    ("Pacific/Truk", "Pacific/Port_Moresby"),
+    ("Pacific/Yap", "Pacific/Port_Moresby"),
+    ("Poland", "Europe/Warsaw"),
+    ("Portugal", "Europe/Lisbon"),
+    ("ROC", "Asia/Taipei"),
+    ("ROK", "Asia/Seoul"),
+    ("Singapore", "Asia/Singapore"),
+    ("Turkey", "Europe/Istanbul"),
+    ("US/Alaska", "America/Anchorage"),
+    ("US/Aleutian", "America/Adak"),
+    ("US/Arizona", "America/Phoenix"),
+    ("US/Central", "America/Chicago"),
+    ("US/East-Indiana", "America/Indiana/Indianapolis"),
+    ("US/Eastern", "America/New_York"),
+    ("US/Hawaii", "Pacific/Honolulu"),
+    ("US/Indiana-Starke", "America/Indiana/Knox"),
+    ("US/Michigan", "America/Detroit"),
+    ("US/Mountain", "America/Denver"),
+    ("US/Pacific", "America/Los_Angeles"),
+    ("US/Samoa", "Pacific/Pago_Pago"),
+    ("W-SU", "Europe/Moscow"),
+];
@@ -0,0 +1,88 @@
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{Local, Timelike};
+use signal_hook::consts::{SIGUSR1, SIGUSR2};
+use signal_hook::iterator::Signals;
+
+use crate::cache;
+use crate::cli::Args;
+use crate::http::Http;
+use crate::lang::Lang;
+use crate::notify::{notifications, Notifier};
+use crate::render::{render, Layout};
+use crate::source::{load_all, Source};
+
+enum Event {
+    /// SIGUSR1: fetch the weather now, skipping the cache.
+    Refresh,
+    /// SIGUSR2: show the next indicator.
+    Cycle,
+}
+
+/// Stay resident and print a new JSON line at the start of every minute, so
+/// Waybar can run wttrbar as a continuous `exec` without an `interval`. The
+/// weather itself is refetched every `--interval` seconds; in between, the
+/// last forecast is rendered again to keep the hourly rows current.
+pub fn run(args: &Args, lang: &Lang, sources: &[Source], http: &Http, layout: &Layout) -> ! {
+    let interval = Duration::from_secs(args.interval);
+    let max_age = Duration::from_secs(args.cache_max_age);
+    let events = listen();
+    let mut indicator = 0;
+    let mut states: Vec<_> = sources.iter().map(Source::state).collect();
+    let mut loaded = false;
+    let mut refresh = false;
+    let mut notifier = args
+        .notify
+        .then(|| Notifier::new(args.cache_dir.clone().unwrap_or_else(cache::default_dir)));
+
+    loop {
+        // A location that failed is retried on its own with backoff, while
+        // its last forecast is shown as stale until it gets too old.
+        let due: Vec<usize> = (0..states.len())
+            .filter(|&i| refresh || states[i].is_due(interval))
+            .collect();
+        if !due.is_empty() {
+            // The cache is only trusted on startup, later refreshes always
+            // go to the provider.
+            let results = load_all(due.iter().map(|&i| &sources[i]), http, loaded || refresh);
+            for (i, result) in due.into_iter().zip(results) {
+                states[i].update(result, max_age);
+            }
+            loaded = true;
+            refresh = false;
+            if let Some(notifier) = &mut notifier {
+                notifier.send(&notifications(&states, args, lang, layout));
+            }
+        }
+
+        if let Some(output) = render(&states, args, lang, layout, &layout.indicators[indicator]) {
+            println!("{}", output);
+        }
+
+        let seconds_left = 60 - u64::from(Local::now().second());
+        match events.recv_timeout(Duration::from_secs(seconds_left)) {
+            Ok(Event::Refresh) => refresh = true,
+            Ok(Event::Cycle) => indicator = (indicator + 1) % layout.indicators.len(),
+            Err(_) => {}
+        }
+    }
+}
+
+fn listen() -> Receiver<Event> {
+    let (sender, receiver) = mpsc::channel();
+    let mut signals = Signals::new([SIGUSR1, SIGUSR2]).expect("Unable to register signal handlers");
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            let event = match signal {
+                SIGUSR1 => Event::Refresh,
+                _ => Event::Cycle,
+            };
+            if sender.send(event).is_err() {
+                break;
+            }
+        }
+    });
+    receiver
+}
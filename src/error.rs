@@ -0,0 +1,221 @@
+use std::io;
+
+use crate::format::escape_markup;
+use crate::lang::Lang;
+use crate::location::LocationKind;
+
+/// Everything that can keep wttrbar from showing the weather. Each kind gets
+/// its own CSS class, so outages can be styled differently from bad config.
+#[derive(Debug)]
+pub enum Error {
+    Network(NetworkError),
+    /// The response isn't the JSON we expected.
+    Parse,
+    /// The provider doesn't know the location, which is kept for the
+    /// tooltip.
+    UnknownLocation(String),
+    /// The provider can't work without `--location`.
+    MissingLocation,
+    /// The response parsed, but lacks something we can't render without.
+    MissingField(&'static str),
+    CacheIo(io::Error),
+    /// The config file can't be read or holds invalid values.
+    Config(String),
+}
+
+#[derive(Debug)]
+pub enum NetworkError {
+    Dns,
+    Tls,
+    Timeout,
+    Unreachable,
+    RateLimited,
+    Server(u16),
+    Status(u16),
+}
+
+impl Error {
+    /// Whether trying again later might help.
+    pub fn is_temporary(&self) -> bool {
+        matches!(
+            self,
+            Self::Network(
+                NetworkError::Dns
+                    | NetworkError::Timeout
+                    | NetworkError::Unreachable
+                    | NetworkError::RateLimited
+                    | NetworkError::Server(_)
+            )
+        )
+    }
+
+    pub fn class(&self) -> &'static str {
+        match self {
+            Self::Network(_) => "error-network",
+            Self::Parse => "error-parse",
+            Self::UnknownLocation(_) => "error-unknown-location",
+            Self::MissingLocation => "error-config",
+            Self::MissingField(_) => "error-missing-field",
+            Self::CacheIo(_) => "error-cache",
+            Self::Config(_) => "error-config",
+        }
+    }
+
+    /// A localized summary followed by the technical details.
+    pub fn tooltip(&self, lang: &Lang, provider: &str) -> String {
+        let summary = match self {
+            Self::Network(_) => lang.error_network(),
+            Self::Parse => lang.error_parse(),
+            Self::UnknownLocation(_) => lang.error_unknown_location(),
+            Self::MissingLocation => lang.error_missing_location(),
+            Self::MissingField(_) => lang.error_missing_field(),
+            Self::CacheIo(_) => lang.error_cache(),
+            Self::Config(_) => lang.error_config(),
+        };
+        format!("{}\n<small>{}</small>", summary, self.details(provider))
+    }
+
+    fn details(&self, provider: &str) -> String {
+        match self {
+            Self::Network(err) => match err {
+                NetworkError::Dns => format!("cannot resolve {} (DNS failure)", provider),
+                NetworkError::Tls => {
+                    format!("secure connection to {} failed (TLS error)", provider)
+                }
+                NetworkError::Timeout => format!("{} did not respond in time", provider),
+                NetworkError::Unreachable => format!("cannot access {}", provider),
+                NetworkError::RateLimited => {
+                    format!("too many requests to {}, try again later", provider)
+                }
+                NetworkError::Server(status) => {
+                    format!("{} server error (HTTP {})", provider, status)
+                }
+                NetworkError::Status(status) => {
+                    format!("unexpected {} response (HTTP {})", provider, status)
+                }
+            },
+            Self::Parse => format!("invalid {} response", provider),
+            Self::UnknownLocation(location) => {
+                // Landmarks and domains are named without their ~ or @.
+                let name = location.trim().trim_start_matches(['~', '@']);
+                let quoted = format!("\"{}\"", escape_markup(name));
+                match LocationKind::of(location) {
+                    LocationKind::Auto => {
+                        format!("{} can't tell where you are, set --location", provider)
+                    }
+                    LocationKind::Landmark => {
+                        format!("{} found no landmark called {}", provider, quoted)
+                    }
+                    LocationKind::Airport => format!(
+                        "{} isn't an airport code {} knows, try the city name",
+                        quoted, provider
+                    ),
+                    LocationKind::Domain => {
+                        format!("{} can't tell where {} is hosted", provider, quoted)
+                    }
+                    LocationKind::PostalCode => {
+                        format!("{} doesn't know the postal code {}", provider, quoted)
+                    }
+                    LocationKind::Name | LocationKind::Coordinates => {
+                        format!("{} doesn't know {}", provider, quoted)
+                    }
+                }
+            }
+            Self::MissingLocation => format!("{} needs --location", provider),
+            Self::MissingField(field) => format!("{} response has no {}", provider, field),
+            Self::CacheIo(err) => format!("cache: {}", err),
+            // Messages from clap and toml contain things like `<LANG>`, which
+            // Waybar would take for markup.
+            Self::Config(message) => escape_markup(message),
+        }
+    }
+}
+
+impl From<NetworkError> for Error {
+    fn from(err: NetworkError) -> Self {
+        Self::Network(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::render_error;
+
+    #[test]
+    fn gives_every_error_a_class_and_tooltip() {
+        let cases = [
+            (
+                Error::Network(NetworkError::Dns),
+                "error-network",
+                "Cannot reach the weather service\n<small>cannot resolve wttr.in (DNS failure)</small>",
+            ),
+            (
+                Error::Parse,
+                "error-parse",
+                "Invalid response from the weather service\n<small>invalid wttr.in response</small>",
+            ),
+            (
+                Error::UnknownLocation("~Atlantis & Co".to_string()),
+                "error-unknown-location",
+                "Unknown location\n<small>wttr.in found no landmark called \"Atlantis &amp; Co\"</small>",
+            ),
+            (
+                Error::MissingLocation,
+                "error-config",
+                "No location configured\n<small>wttr.in needs --location</small>",
+            ),
+            (
+                Error::MissingField("current_condition"),
+                "error-missing-field",
+                "Incomplete weather data\n<small>wttr.in response has no current_condition</small>",
+            ),
+            (
+                Error::CacheIo(io::Error::other("disk full")),
+                "error-cache",
+                "Cannot write the weather cache\n<small>cache: disk full</small>",
+            ),
+            (
+                Error::Config("invalid value for <LANG>".to_string()),
+                "error-config",
+                "Invalid configuration\n<small>invalid value for &lt;LANG&gt;</small>",
+            ),
+        ];
+        for (err, class, tooltip) in cases {
+            assert_eq!(err.class(), class);
+            assert_eq!(err.tooltip(&Lang::EN, "wttr.in"), tooltip);
+
+            let output = render_error(&err, &Lang::EN, "wttr.in");
+            assert_eq!(output["class"], serde_json::json!(["error", class]));
+            assert_eq!(output["tooltip"], tooltip);
+        }
+    }
+
+    #[test]
+    fn describes_network_failures() {
+        let details = |err: NetworkError| Error::Network(err).details("wttr.in");
+        assert_eq!(
+            details(NetworkError::Tls),
+            "secure connection to wttr.in failed (TLS error)"
+        );
+        assert_eq!(
+            details(NetworkError::Timeout),
+            "wttr.in did not respond in time"
+        );
+        assert_eq!(details(NetworkError::Unreachable), "cannot access wttr.in");
+        assert_eq!(
+            details(NetworkError::RateLimited),
+            "too many requests to wttr.in, try again later"
+        );
+        assert_eq!(
+            details(NetworkError::Server(503)),
+            "wttr.in server error (HTTP 503)"
+        );
+        assert_eq!(
+            details(NetworkError::Status(404)),
+            "unexpected wttr.in response (HTTP 404)"
+        );
+        assert!(Error::Network(NetworkError::Server(503)).is_temporary());
+        assert!(!Error::Network(NetworkError::Tls).is_temporary());
+    }
+}
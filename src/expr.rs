@@ -0,0 +1,406 @@
+use std::cmp::Ordering;
+
+/// The content of a `{...}` placeholder: a key, arithmetic on keys and
+/// numbers, an optional comparison with a `?then:else` conditional, and a
+/// chain of `|filter:argument` filters. For example `{temp_C - FeelsLikeC}`,
+/// `{precipMM>0?☔:}` or `{uvIndex|default:-}`.
+#[derive(Debug, Clone)]
+pub struct Expr {
+    value: Value,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Key(String),
+    Number(f64),
+    Negate(Box<Value>),
+    Binary(Box<Value>, char, Box<Value>),
+    Compare(Box<Value>, Comparison, Box<Value>),
+    Conditional(Box<Value>, String, String),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone)]
+enum Filter {
+    Round,
+    Pad(usize),
+    Default(String),
+    Upper,
+    Lower,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            source,
+            position: 0,
+        };
+        let value = parser.conditional()?;
+        // Whatever the parser left starts the filter chain.
+        let filters = match parser.rest().strip_prefix('|') {
+            Some(filters) => filters
+                .split('|')
+                .map(Filter::parse)
+                .collect::<Result<_, _>>()?,
+            None => vec![],
+        };
+        Ok(Expr { value, filters })
+    }
+
+    /// Evaluate the expression, looking up keys with `lookup`. Missing keys
+    /// and arithmetic on things that aren't numbers give an empty result.
+    pub fn eval(&self, lookup: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut result = self.value.eval(lookup);
+        for filter in &self.filters {
+            result = filter.apply(result);
+        }
+        result.unwrap_or_default()
+    }
+
+    /// Whether the expression holds: anything but an empty result or zero.
+    pub fn test(&self, lookup: &dyn Fn(&str) -> Option<String>) -> bool {
+        is_true(&self.eval(lookup))
+    }
+}
+
+impl Value {
+    fn eval(&self, lookup: &dyn Fn(&str) -> Option<String>) -> Option<String> {
+        match self {
+            Value::Key(key) => lookup(key),
+            Value::Number(number) => Some(format_number(*number)),
+            Value::Negate(value) => Some(format_number(-value.number(lookup)?)),
+            Value::Binary(left, operator, right) => {
+                let (left, right) = (left.number(lookup)?, right.number(lookup)?);
+                Some(format_number(match operator {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    _ => left / right,
+                }))
+            }
+            Value::Compare(left, comparison, right) => {
+                let matches = compare(left.eval(lookup), *comparison, right.eval(lookup));
+                Some(if matches { "1" } else { "0" }.to_string())
+            }
+            Value::Conditional(condition, then, otherwise) => {
+                let set = condition.eval(lookup).is_some_and(|value| is_true(&value));
+                Some(if set { then } else { otherwise }.clone())
+            }
+        }
+    }
+
+    fn number(&self, lookup: &dyn Fn(&str) -> Option<String>) -> Option<f64> {
+        self.eval(lookup)?.trim().parse().ok()
+    }
+}
+
+fn is_true(value: &str) -> bool {
+    !value.is_empty() && value.trim().parse() != Ok(0.0)
+}
+
+fn compare(left: Option<String>, comparison: Comparison, right: Option<String>) -> bool {
+    let (Some(left), Some(right)) = (left, right) else {
+        return false;
+    };
+    let ordering = match (left.trim().parse::<f64>(), right.trim().parse::<f64>()) {
+        (Ok(left), Ok(right)) => left.partial_cmp(&right),
+        _ => Some(left.cmp(&right)),
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match comparison {
+        Comparison::Less => ordering == Ordering::Less,
+        Comparison::LessOrEqual => ordering != Ordering::Greater,
+        Comparison::Greater => ordering == Ordering::Greater,
+        Comparison::GreaterOrEqual => ordering != Ordering::Less,
+        Comparison::Equal => ordering == Ordering::Equal,
+        Comparison::NotEqual => ordering != Ordering::Equal,
+    }
+}
+
+/// Whole numbers without a fraction, anything else with up to two decimals.
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 {
+        format!("{}", number)
+    } else {
+        let formatted = format!("{:.2}", number);
+        formatted
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string()
+    }
+}
+
+impl Filter {
+    fn parse(source: &str) -> Result<Self, String> {
+        let (name, argument) = match source.split_once(':') {
+            Some((name, argument)) => (name.trim(), Some(argument)),
+            None => (source.trim(), None),
+        };
+        match (name, argument) {
+            ("round", None) => Ok(Filter::Round),
+            ("upper", None) => Ok(Filter::Upper),
+            ("lower", None) => Ok(Filter::Lower),
+            ("pad", Some(width)) => width
+                .trim()
+                .parse()
+                .map(Filter::Pad)
+                .map_err(|_| format!("pad needs a width, not {:?}", width)),
+            ("default", Some(default)) => Ok(Filter::Default(default.to_string())),
+            ("pad" | "default", None) => {
+                Err(format!("{} needs an argument, like {}:3", name, name))
+            }
+            ("round" | "upper" | "lower", Some(_)) => Err(format!("{} takes no argument", name)),
+            _ => Err(format!("unknown filter {:?}", name)),
+        }
+    }
+
+    fn apply(&self, value: Option<String>) -> Option<String> {
+        match self {
+            Filter::Round => value.map(|value| match value.trim().parse::<f64>() {
+                Ok(number) => format_number(number.round()),
+                Err(_) => value,
+            }),
+            Filter::Pad(width) => Some(format!("{:>width$}", value.unwrap_or_default())),
+            Filter::Default(default) => match value {
+                Some(value) if !value.is_empty() => Some(value),
+                _ => Some(default.clone()),
+            },
+            Filter::Upper => value.map(|value| value.to_uppercase()),
+            Filter::Lower => value.map(|value| value.to_lowercase()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.position..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.position += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error(&self, expected: &str) -> String {
+        match self.rest().chars().next() {
+            Some(found) => format!(
+                "expected {} at position {}, found {:?}",
+                expected, self.position, found
+            ),
+            None => format!("expected {} at the end", expected),
+        }
+    }
+
+    /// `comparison ('?' then (':' else)?)?`, the branches being trimmed
+    /// plain text. A `|` in the then text belongs to it, the filters start
+    /// after the else text.
+    fn conditional(&mut self) -> Result<Value, String> {
+        let condition = self.comparison()?;
+        if !self.eat("?") {
+            self.skip_whitespace();
+            return if self.rest().is_empty() || self.rest().starts_with('|') {
+                Ok(condition)
+            } else {
+                Err(self.error("an operator"))
+            };
+        }
+        let rest = self.rest();
+        let then_end = rest
+            .find(':')
+            .or_else(|| rest.find('|'))
+            .unwrap_or(rest.len());
+        let then = rest[..then_end].trim().to_string();
+        let mut end = then_end;
+        let mut otherwise = String::new();
+        if let Some(after) = rest[then_end..].strip_prefix(':') {
+            let length = after.find('|').unwrap_or(after.len());
+            otherwise = after[..length].trim().to_string();
+            end += 1 + length;
+        }
+        self.position += end;
+        Ok(Value::Conditional(Box::new(condition), then, otherwise))
+    }
+
+    fn comparison(&mut self) -> Result<Value, String> {
+        let left = self.sum()?;
+        let comparisons = [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("==", Comparison::Equal),
+            ("!=", Comparison::NotEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+        ];
+        for (token, comparison) in comparisons {
+            if self.eat(token) {
+                let right = self.sum()?;
+                return Ok(Value::Compare(Box::new(left), comparison, Box::new(right)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut value = self.product()?;
+        loop {
+            let operator = if self.eat("+") {
+                '+'
+            } else if self.eat("-") {
+                '-'
+            } else {
+                return Ok(value);
+            };
+            value = Value::Binary(Box::new(value), operator, Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Value, String> {
+        let mut value = self.atom()?;
+        loop {
+            let operator = if self.eat("*") {
+                '*'
+            } else if self.eat("/") {
+                '/'
+            } else {
+                return Ok(value);
+            };
+            value = Value::Binary(Box::new(value), operator, Box::new(self.atom()?));
+        }
+    }
+
+    fn atom(&mut self) -> Result<Value, String> {
+        if self.eat("(") {
+            let value = self.sum()?;
+            if !self.eat(")") {
+                return Err(self.error("')'"));
+            }
+            return Ok(value);
+        }
+        if self.eat("-") {
+            return Ok(Value::Negate(Box::new(self.atom()?)));
+        }
+        self.skip_whitespace();
+        let rest = self.rest();
+        let key_length = |rest: &str| {
+            rest.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.'))
+                .unwrap_or(rest.len())
+        };
+        let mut length = key_length(rest);
+        // `hour+3.tempC` is a key, not an addition.
+        if &rest[..length] == "hour" {
+            if let Some(offset) = rest[length..].strip_prefix('+').and_then(|after| {
+                let digits = after.find(|c: char| !c.is_ascii_digit())?;
+                (digits > 0 && after[digits..].starts_with('.')).then_some(digits)
+            }) {
+                length += 1 + offset;
+                length += key_length(&rest[length..]);
+            }
+        }
+        if length == 0 {
+            return Err(self.error("a key or a number"));
+        }
+        let token = &self.source[self.position..self.position + length];
+        self.position += length;
+        if token.starts_with(|c: char| c.is_ascii_digit()) {
+            token
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("{:?} is not a number", token))
+        } else {
+            Ok(Value::Key(token.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str) -> String {
+        let lookup = |key: &str| match key {
+            "temp_C" => Some("7".to_string()),
+            "FeelsLikeC" => Some("4".to_string()),
+            "precipMM" => Some("0.3".to_string()),
+            "pressure" => Some("1013.6".to_string()),
+            "weatherDesc" => Some("Light rain".to_string()),
+            _ => None,
+        };
+        Expr::parse(source).unwrap().eval(&lookup)
+    }
+
+    #[test]
+    fn evaluates_arithmetic() {
+        assert_eq!(eval("temp_C - FeelsLikeC"), "3");
+        assert_eq!(eval("(temp_C + 1) * 2"), "16");
+        assert_eq!(eval("-temp_C / 2"), "-3.5");
+        assert_eq!(eval("temp_C - missing"), "");
+    }
+
+    #[test]
+    fn applies_filters() {
+        assert_eq!(eval("pressure|round"), "1014");
+        assert_eq!(eval("temp_C|pad:3"), "  7");
+        assert_eq!(eval("uvIndex|default:-"), "-");
+        assert_eq!(eval("weatherDesc|upper"), "LIGHT RAIN");
+    }
+
+    #[test]
+    fn evaluates_conditionals() {
+        assert_eq!(eval("precipMM>0?☔:"), "☔");
+        assert_eq!(eval("precipMM > 1 ? ☔ : dry"), "dry");
+        assert_eq!(eval("precipMM>0?☔|rain:"), "☔|rain");
+        assert_eq!(eval("precipMM>1?wet:dry|upper"), "DRY");
+        assert_eq!(eval("precipMM>0?wet|upper"), "WET");
+        assert_eq!(eval("temp_C<=7?cold:warm"), "cold");
+        assert_eq!(eval("missing?yes:no"), "no");
+    }
+
+    #[test]
+    fn reads_hour_offsets_as_keys() {
+        let lookup = |key: &str| (key == "hour+3.tempC").then(|| "5".to_string());
+        assert_eq!(Expr::parse("hour+3.tempC").unwrap().eval(&lookup), "5");
+        assert_eq!(Expr::parse("hour+3.tempC + 1").unwrap().eval(&lookup), "6");
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        assert_eq!(
+            Expr::parse("temp_C -").unwrap_err(),
+            "expected a key or a number at the end"
+        );
+        assert_eq!(
+            Expr::parse("temp_C FeelsLikeC").unwrap_err(),
+            "expected an operator at position 7, found 'F'"
+        );
+        assert!(Expr::parse("(temp_C").is_err());
+        assert!(Expr::parse("temp_C|pad").is_err());
+        assert!(Expr::parse("temp_C|bold").is_err());
+    }
+}
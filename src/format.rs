@@ -1,12 +1,60 @@
 use chrono::prelude::*;
 use serde_json::Value;
-use std::collections::HashMap;
 
-use crate::constants::{ICON_PLACEHOLDER, MOON_PHASES, MOON_PHASES_NERD};
+use crate::constants::{
+    MOON_PHASES, MOON_PHASES_NERD, WEATHER_CODES, WEATHER_CODES_NERD, WEATHER_CODES_NIGHT,
+    WEATHER_CODES_NIGHT_NERD, WIND_DIRECTIONS, WIND_DIRECTIONS_NERD,
+};
 use crate::lang::Lang;
+use crate::model::{CurrentCondition, Hourly};
+use crate::template::Template;
+
+/// Escape text for Waybar's Pango markup.
+pub fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Round `value` to `decimals`, halves away from zero, without showing "-0"
+/// for small negative values.
+pub fn format_rounded(value: f64, decimals: usize) -> String {
+    let factor = 10f64.powi(decimals as i32);
+    let formatted = format!("{:.*}", decimals, (value * factor).round() / factor);
+    if formatted
+        .trim_start_matches('-')
+        .chars()
+        .all(|c| c == '0' || c == '.')
+    {
+        formatted.trim_start_matches('-').to_string()
+    } else {
+        formatted
+    }
+}
 
+/// Percent-encode every byte of `text` that `keep` doesn't accept as it is.
+pub fn percent_encode(text: &str, keep: impl Fn(u8) -> bool) -> String {
+    let mut encoded = String::new();
+    for byte in text.bytes() {
+        if keep(byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded += &format!("%{:02X}", byte);
+        }
+    }
+    encoded
+}
+
+/// Format a wttr.in hourly time like `900` or `1430`. Minutes are only shown
+/// when there are any, which happens after converting to another time zone.
 pub fn format_time(time: &str, ampm: bool) -> String {
-    let hour = time.replace("00", "").parse::<i32>().unwrap();
+    let time = time.trim().parse::<u32>().unwrap_or_default();
+    let (hour, minute) = (time / 100, time % 100);
+    let minutes = if minute == 0 {
+        String::new()
+    } else {
+        format!(":{:02}", minute)
+    };
 
     if ampm {
         let am_or_pm = if hour >= 12 { "pm" } else { "am" };
@@ -15,50 +63,130 @@ pub fn format_time(time: &str, ampm: bool) -> String {
         } else {
             hour % 12
         };
-        format!("{: <4}", format!("{}{}", hour12, am_or_pm))
+        format!("{: <4}", format!("{}{}{}", hour12, minutes, am_or_pm))
     } else {
-        format!("{:02}", hour)
+        format!("{:02}{}", hour, minutes)
     }
 }
 
-pub fn get_observation_time(current_condition: &Value, ampm: bool) -> Option<String> {
-    let obs_time = current_condition["observation_time"].as_str()?;
-    let time = NaiveTime::parse_from_str(obs_time, "%I:%M %p").ok()?;
-
-    let utc_today = Utc::now().date_naive();
-    let utc_datetime = utc_today.and_time(time);
-    let local_datetime = Local.from_utc_datetime(&utc_datetime);
-    let local_time = local_datetime.time();
+/// When the current conditions were observed, at the forecast location or,
+/// with `local_time`, on this machine.
+pub fn get_observation_time(
+    current_condition: &CurrentCondition,
+    ampm: bool,
+    local_time: bool,
+) -> Option<String> {
+    let location_time = current_condition
+        .local_obs_date_time
+        .as_deref()
+        .and_then(|time| NaiveDateTime::parse_from_str(time, OBS_DATE_TIME_FORMAT).ok());
+    let time = match location_time {
+        Some(time) => shown_time(current_condition, time, local_time),
+        None => {
+            let obs_time = current_condition.observation_time.as_deref()?;
+            let time = NaiveTime::parse_from_str(obs_time, "%I:%M %p").ok()?;
+            let observed = observed_at(time, Utc::now().naive_utc());
+            Local.from_utc_datetime(&observed).naive_local()
+        }
+    };
 
     Some(if ampm {
-        local_time.format("%I:%M %p").to_string()
+        time.format("%I:%M %p").to_string()
     } else {
-        local_time.format("%H:%M").to_string()
+        time.format("%H:%M").to_string()
     })
 }
 
+/// The UTC date and time of an observation made at the UTC `time`. The
+/// observation is in the past, so shortly after midnight it was made
+/// yesterday.
+fn observed_at(time: NaiveTime, now: NaiveDateTime) -> NaiveDateTime {
+    let observed = now.date().and_time(time);
+    if observed > now + chrono::Duration::minutes(5) {
+        observed - chrono::Duration::days(1)
+    } else {
+        observed
+    }
+}
+
+const OBS_DATE_TIME_FORMAT: &str = "%Y-%m-%d %I:%M %p";
+
+/// The UTC offset of the forecast location: the difference between
+/// `localObsDateTime` and the UTC `observation_time`, rounded to 15 minutes.
+pub fn location_offset(current_condition: &CurrentCondition) -> Option<FixedOffset> {
+    let local = NaiveDateTime::parse_from_str(
+        current_condition.local_obs_date_time.as_deref()?,
+        OBS_DATE_TIME_FORMAT,
+    )
+    .ok()?;
+    let utc = NaiveTime::parse_from_str(current_condition.observation_time.as_deref()?, "%I:%M %p")
+        .ok()?;
+    // Only the UTC time is known, not its date, so the offset is taken to be
+    // between -12 and +14 hours like every real one.
+    let mut seconds = (local.time() - utc).num_seconds();
+    if seconds > 14 * 3600 {
+        seconds -= 24 * 3600;
+    } else if seconds < -12 * 3600 {
+        seconds += 24 * 3600;
+    }
+    let quarters = (seconds as f64 / 900.0).round() as i32;
+    FixedOffset::east_opt(quarters * 900)
+}
+
+/// The current time at the forecast location, which the hourly forecast is
+/// given in, or on this machine when the location's offset is unknown.
+pub fn location_now(current_condition: &CurrentCondition) -> NaiveDateTime {
+    match location_offset(current_condition) {
+        Some(offset) => Utc::now().with_timezone(&offset).naive_local(),
+        None => Local::now().naive_local(),
+    }
+}
+
+/// A time at the forecast location as it's shown: unchanged, or on this
+/// machine's clock with `local_time`.
+pub fn shown_time(
+    current_condition: &CurrentCondition,
+    time: NaiveDateTime,
+    local_time: bool,
+) -> NaiveDateTime {
+    location_offset(current_condition)
+        .filter(|_| local_time)
+        .and_then(|offset| offset.from_local_datetime(&time).single())
+        .map_or(time, |time| time.with_timezone(&Local).naive_local())
+}
+
+/// A moment, like when the forecast was fetched, on the clock of the forecast
+/// location or, with `local_time`, of this machine.
+pub fn shown_instant(
+    current_condition: &CurrentCondition,
+    instant: DateTime<Utc>,
+    local_time: bool,
+) -> NaiveDateTime {
+    match location_offset(current_condition).filter(|_| !local_time) {
+        Some(offset) => instant.with_timezone(&offset).naive_local(),
+        None => instant.with_timezone(&Local).naive_local(),
+    }
+}
+
 pub fn format_temp(temp: &str) -> String {
     format!("{: >3}°", temp)
 }
 
-pub fn format_chances(hour: &serde_json::Value, lang: &Lang) -> String {
-    let chances: HashMap<&str, String> = [
-        ("chanceoffog", lang.fog()),
-        ("chanceoffrost", lang.frost()),
-        ("chanceofovercast", lang.overcast()),
-        ("chanceofrain", lang.rain()),
-        ("chanceofsnow", lang.snow()),
-        ("chanceofsunshine", lang.sunshine()),
-        ("chanceofthunder", lang.thunder()),
-        ("chanceofwindy", lang.wind()),
-    ]
-    .iter()
-    .cloned()
-    .collect();
+pub fn format_chances(hour: &Hourly, lang: &Lang) -> String {
+    let chances = [
+        (&hour.chanceoffog, lang.fog()),
+        (&hour.chanceoffrost, lang.frost()),
+        (&hour.chanceofovercast, lang.overcast()),
+        (&hour.chanceofrain, lang.rain()),
+        (&hour.chanceofsnow, lang.snow()),
+        (&hour.chanceofsunshine, lang.sunshine()),
+        (&hour.chanceofthunder, lang.thunder()),
+        (&hour.chanceofwindy, lang.wind()),
+    ];
 
     let mut conditions = vec![];
-    for (event, name) in chances.iter() {
-        if let Some(chance) = hour[event].as_str() {
+    for (chance, name) in chances.iter() {
+        if let Some(chance) = chance {
             if let Ok(chance_value) = chance.parse::<u32>() {
                 if chance_value > 0 {
                     conditions.push((name, chance_value));
@@ -74,17 +202,33 @@ pub fn format_chances(hour: &serde_json::Value, lang: &Lang) -> String {
         .join(", ")
 }
 
-pub fn format_ampm_time(day: &serde_json::Value, key: &str, ampm: bool) -> String {
+pub fn format_ampm_time(time: &str, ampm: bool) -> Option<String> {
     if ampm {
-        day["astronomy"][0][key].as_str().unwrap().to_string()
+        Some(time.to_string())
     } else {
-        NaiveTime::parse_from_str(day["astronomy"][0][key].as_str().unwrap(), "%I:%M %p")
-            .unwrap()
-            .format("%H:%M")
-            .to_string()
+        NaiveTime::parse_from_str(time, "%I:%M %p")
+            .ok()
+            .map(|time| time.format("%H:%M").to_string())
     }
 }
 
+pub fn format_weather_icon(code: Option<&str>, nerd: bool, night: bool) -> &'static str {
+    let (table, night_table) = if nerd {
+        (WEATHER_CODES_NERD, WEATHER_CODES_NIGHT_NERD)
+    } else {
+        (WEATHER_CODES, WEATHER_CODES_NIGHT)
+    };
+    let Some(code) = code.and_then(|code| code.parse::<i32>().ok()) else {
+        return "?";
+    };
+    night
+        .then(|| night_table.iter().find(|(c, _)| *c == code))
+        .flatten()
+        .or_else(|| table.iter().find(|(c, _)| *c == code))
+        .map(|(_, symbol)| *symbol)
+        .unwrap_or("?")
+}
+
 pub fn format_moon_phase_icon(phase: &str, nerd: bool) -> &str {
     let fallback = if nerd { "󰽤" } else { "🌑" };
     let table = if nerd { MOON_PHASES_NERD } else { MOON_PHASES };
@@ -95,16 +239,24 @@ pub fn format_moon_phase_icon(phase: &str, nerd: bool) -> &str {
         .unwrap_or(fallback)
 }
 
+pub fn format_wind_direction_icon(degree: &str, nerd: bool) -> Option<&'static str> {
+    let table = if nerd {
+        WIND_DIRECTIONS_NERD
+    } else {
+        WIND_DIRECTIONS
+    };
+    let degree = degree.trim().parse::<f64>().ok()?.rem_euclid(360.0);
+    let index = ((degree + 22.5) / 45.0) as usize % table.len();
+    Some(table[index])
+}
+
 pub fn format_indicator(
     weather_conditions: &Value,
     area: &Value,
-    expression: String,
+    forecast: &Value,
+    expression: &Template,
     weather_icon: &&str,
 ) -> String {
-    if !weather_conditions.is_object() {
-        return String::new();
-    }
-
     let weather_map = match weather_conditions.as_object() {
         Some(w) => w,
         None => return String::new(),
@@ -113,31 +265,11 @@ pub fn format_indicator(
     if let Some(area_map) = area.as_object() {
         combined_map.extend(area_map.clone());
     }
-
-    let mut formatted_indicator = expression.to_string();
-    combined_map
-        .iter()
-        .map(|condition| ("{".to_owned() + condition.0 + "}", condition.1))
-        .for_each(|condition| {
-            if formatted_indicator.contains(condition.0.as_str()) {
-                let condition_value = if condition.1.is_array() {
-                    condition.1.as_array().and_then(|vec| {
-                        vec[0]
-                            .as_object()
-                            .and_then(|value_map| value_map["value"].as_str())
-                    })
-                } else {
-                    condition.1.as_str()
-                }
-                .unwrap_or("");
-                formatted_indicator =
-                    formatted_indicator.replace(condition.0.as_str(), condition_value)
-            }
-        });
-    if formatted_indicator.contains(ICON_PLACEHOLDER) {
-        formatted_indicator = formatted_indicator.replace(ICON_PLACEHOLDER, weather_icon)
+    if let Some(forecast_map) = forecast.as_object() {
+        combined_map.extend(forecast_map.clone());
     }
-    formatted_indicator
+    combined_map.insert("ICON".to_string(), Value::from(*weather_icon));
+    expression.render(&Value::Object(combined_map))
 }
 
 #[cfg(test)]
@@ -145,6 +277,22 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn rounds_without_negative_zero() {
+        assert_eq!(format_rounded(7.4, 0), "7");
+        assert_eq!(format_rounded(2.5, 0), "3");
+        assert_eq!(format_rounded(-0.3, 0), "0");
+        assert_eq!(format_rounded(-0.004, 2), "0.00");
+        assert_eq!(format_rounded(-33.92487, 1), "-33.9");
+    }
+
+    #[test]
+    fn percent_encodes_rejected_bytes() {
+        let keep = |byte: u8| byte.is_ascii_alphanumeric();
+        assert_eq!(percent_encode("São Paulo", keep), "S%C3%A3o%20Paulo");
+        assert_eq!(percent_encode("", keep), "");
+    }
+
     #[test]
     fn maps_all_emoji_moon_phases() {
         let cases = [
@@ -187,6 +335,29 @@ mod tests {
         assert_eq!(format_moon_phase_icon("Unknown", true), "󰽤");
     }
 
+    #[test]
+    fn uses_night_icons_for_clear_skies() {
+        assert_eq!(format_weather_icon(Some("113"), false, false), "☀️");
+        assert_eq!(format_weather_icon(Some("113"), false, true), "🌙");
+        assert_eq!(format_weather_icon(Some("116"), false, true), "🌙☁️");
+        assert_ne!(
+            format_weather_icon(Some("116"), false, true),
+            format_weather_icon(Some("119"), false, true)
+        );
+        assert_eq!(format_weather_icon(Some("116"), true, true), "󰼱");
+        assert_eq!(format_weather_icon(Some("296"), false, true), "🌧️");
+        assert_eq!(format_weather_icon(None, false, true), "?");
+    }
+
+    #[test]
+    fn maps_wind_directions() {
+        assert_eq!(format_wind_direction_icon("0", false), Some("⬇️"));
+        assert_eq!(format_wind_direction_icon("350", false), Some("⬇️"));
+        assert_eq!(format_wind_direction_icon("225", false), Some("↗️"));
+        assert_eq!(format_wind_direction_icon("270", true), Some("󰁔"));
+        assert_eq!(format_wind_direction_icon("calm", false), None);
+    }
+
     // --- format_time ---
 
     #[test]
@@ -196,6 +367,13 @@ mod tests {
         assert_eq!(format_time("0", false), "00");
     }
 
+    #[test]
+    fn format_time_with_minutes() {
+        assert_eq!(format_time("2000", false), "20");
+        assert_eq!(format_time("1430", false), "14:30");
+        assert_eq!(format_time("1430", true).trim(), "2:30pm");
+    }
+
     #[test]
     fn format_time_ampm() {
         assert_eq!(format_time("0", true).trim(), "12am");
@@ -215,6 +393,10 @@ mod tests {
 
     // --- format_indicator: custom_indicator ---
 
+    fn template(source: &str) -> Template {
+        Template::parse(source).unwrap()
+    }
+
     fn make_conditions() -> serde_json::Value {
         json!({
             "temp_C": "7",
@@ -231,7 +413,13 @@ mod tests {
     fn custom_indicator_substitutes_placeholders() {
         let conditions = make_conditions();
         let area = json!(null);
-        let result = format_indicator(&conditions, &area, "{temp_C}°C".to_string(), &"☀️");
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &json!(null),
+            &template("{temp_C}°C"),
+            &"☀️",
+        );
         assert_eq!(result, "7°C");
     }
 
@@ -239,7 +427,13 @@ mod tests {
     fn custom_indicator_substitutes_icon_placeholder() {
         let conditions = make_conditions();
         let area = json!(null);
-        let result = format_indicator(&conditions, &area, "{ICON} {temp_C}".to_string(), &"☀️");
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &json!(null),
+            &template("{ICON} {temp_C}"),
+            &"☀️",
+        );
         assert_eq!(result, "☀️ 7");
     }
 
@@ -250,19 +444,58 @@ mod tests {
         let result = format_indicator(
             &conditions,
             &area,
-            "{ICON}{temp_C}({FeelsLikeC})".to_string(),
+            &json!(null),
+            &template("{ICON}{temp_C}({FeelsLikeC})"),
             &"☀️",
         );
         assert_eq!(result, "☀️7(-2)");
     }
 
+    #[test]
+    fn custom_indicator_evaluates_expressions() {
+        let conditions = make_conditions();
+        let area = json!(null);
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &json!(null),
+            &template("{temp_C - FeelsLikeC|pad:3} {uvIndex|default:-}"),
+            &"☀️",
+        );
+        assert_eq!(result, "  9 -");
+    }
+
+    #[test]
+    fn custom_indicator_with_forecast_fields() {
+        let conditions = make_conditions();
+        let area = json!(null);
+        let forecast = json!({
+            "today": {"maxtempC": "12"},
+            "hour+3": {"chanceofrain": "80"}
+        });
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &forecast,
+            &template("{today.maxtempC}° {hour+3.chanceofrain}% {tomorrow.maxtempC|default:-}"),
+            &"☀️",
+        );
+        assert_eq!(result, "12° 80% -");
+    }
+
     #[test]
     fn custom_indicator_null_area_does_not_return_empty() {
         // This is the regression test for the bug where nearest_area being null
         // caused format_indicator to return an empty string.
         let conditions = make_conditions();
         let area = json!(null);
-        let result = format_indicator(&conditions, &area, "{temp_C}".to_string(), &"☀️");
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &json!(null),
+            &template("{temp_C}"),
+            &"☀️",
+        );
         assert_eq!(result, "7");
     }
 
@@ -270,7 +503,13 @@ mod tests {
     fn custom_indicator_with_area_fields() {
         let conditions = make_conditions();
         let area = json!({"areaName": [{"value": "London"}]});
-        let result = format_indicator(&conditions, &area, "{temp_C} {areaName}".to_string(), &"☀️");
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &json!(null),
+            &template("{temp_C} {areaName}"),
+            &"☀️",
+        );
         assert_eq!(result, "7 London");
     }
 
@@ -278,15 +517,22 @@ mod tests {
     fn custom_indicator_invalid_conditions_returns_empty() {
         let conditions = json!(null);
         let area = json!(null);
-        let result = format_indicator(&conditions, &area, "{temp_C}".to_string(), &"☀️");
+        let result = format_indicator(
+            &conditions,
+            &area,
+            &json!(null),
+            &template("{temp_C}"),
+            &"☀️",
+        );
         assert_eq!(result, "");
     }
 
     #[test]
     fn test_get_observation_time() {
-        let condition = json!({
+        let condition: CurrentCondition = serde_json::from_value(json!({
             "observation_time": "08:26 PM"
-        });
+        }))
+        .unwrap();
 
         // Dynamic expected local time based on system offset
         let time = NaiveTime::parse_from_str("08:26 PM", "%I:%M %p").unwrap();
@@ -297,10 +543,79 @@ mod tests {
         let expected_24h = local_time.format("%H:%M").to_string();
         let expected_ampm = local_time.format("%I:%M %p").to_string();
 
-        assert_eq!(get_observation_time(&condition, false), Some(expected_24h));
-        assert_eq!(get_observation_time(&condition, true), Some(expected_ampm));
+        assert_eq!(
+            get_observation_time(&condition, false, true),
+            Some(expected_24h)
+        );
+        assert_eq!(
+            get_observation_time(&condition, true, true),
+            Some(expected_ampm)
+        );
+
+        let condition_empty = CurrentCondition::default();
+        assert_eq!(get_observation_time(&condition_empty, false, false), None);
+    }
+
+    #[test]
+    fn uses_the_location_time_zone() {
+        let tokyo: CurrentCondition = serde_json::from_value(json!({
+            "observation_time": "11:45 PM",
+            "localObsDateTime": "2026-10-19 08:45 AM"
+        }))
+        .unwrap();
+        assert_eq!(location_offset(&tokyo), FixedOffset::east_opt(9 * 3600));
+        assert_eq!(
+            get_observation_time(&tokyo, false, false).as_deref(),
+            Some("08:45")
+        );
+
+        let new_york: CurrentCondition = serde_json::from_value(json!({
+            "observation_time": "02:10 AM",
+            "localObsDateTime": "2026-10-18 10:10 PM"
+        }))
+        .unwrap();
+        assert_eq!(location_offset(&new_york), FixedOffset::west_opt(4 * 3600));
+        assert_eq!(location_offset(&CurrentCondition::default()), None);
+
+        let morning = NaiveDate::from_ymd_opt(2026, 10, 19)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap();
+        assert_eq!(shown_time(&tokyo, morning, false), morning);
+        let utc = FixedOffset::east_opt(9 * 3600)
+            .unwrap()
+            .from_local_datetime(&morning)
+            .unwrap();
+        assert_eq!(
+            shown_time(&tokyo, morning, true),
+            utc.with_timezone(&Local).naive_local()
+        );
+        assert_eq!(
+            shown_instant(&tokyo, utc.with_timezone(&Utc), false),
+            morning
+        );
+    }
 
-        let condition_empty = json!({});
-        assert_eq!(get_observation_time(&condition_empty, false), None);
+    #[test]
+    fn dates_observations_before_midnight_yesterday() {
+        let date = |day| NaiveDate::from_ymd_opt(2026, 10, day).unwrap();
+        let time = |hour, minute| NaiveTime::from_hms_opt(hour, minute, 0).unwrap();
+
+        let now = date(19).and_time(time(0, 10));
+        assert_eq!(
+            observed_at(time(23, 50), now),
+            date(18).and_time(time(23, 50))
+        );
+        assert_eq!(observed_at(time(0, 5), now), date(19).and_time(time(0, 5)));
+        // Clocks differ a little, an observation from "the future" is today.
+        assert_eq!(
+            observed_at(time(0, 13), now),
+            date(19).and_time(time(0, 13))
+        );
+        let evening = date(18).and_time(time(20, 30));
+        assert_eq!(
+            observed_at(time(20, 26), evening),
+            date(18).and_time(time(20, 26))
+        );
     }
 }
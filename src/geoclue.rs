@@ -0,0 +1,232 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use zbus::blocking::{proxy, Connection, Proxy};
+use zbus::proxy::CacheProperties;
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::format::format_rounded;
+
+/// The `--location` that asks GeoClue2 where we are.
+pub const AUTO_GEOCLUE: &str = "auto-geoclue";
+
+const SERVICE: &str = "org.freedesktop.GeoClue2";
+
+/// How long GeoClue2 gets to find the location.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ask GeoClue2 on the system bus for the current position, rounded to
+/// `precision` decimals, as a `lat,lon` location.
+pub fn locate(precision: u8) -> zbus::Result<String> {
+    locate_on(&Connection::system()?, precision)
+}
+
+fn locate_on(connection: &Connection, precision: u8) -> zbus::Result<String> {
+    let manager = proxy(
+        connection,
+        "/org/freedesktop/GeoClue2/Manager",
+        "org.freedesktop.GeoClue2.Manager",
+    )?;
+    let client_path: OwnedObjectPath = manager.call("GetClient", &())?;
+    let client = proxy(
+        connection,
+        client_path.as_str(),
+        "org.freedesktop.GeoClue2.Client",
+    )?;
+    client.set_property("DesktopId", "wttrbar")?;
+    client.set_property("RequestedAccuracyLevel", accuracy_level(precision))?;
+    client.call_method("Start", &())?;
+
+    let result = wait_for_location(connection, &client, precision);
+    let _ = client.call_method("Stop", &());
+    result
+}
+
+fn wait_for_location(
+    connection: &Connection,
+    client: &Proxy,
+    precision: u8,
+) -> zbus::Result<String> {
+    let started = Instant::now();
+    // The client's Location stays `/` until GeoClue2 has a fix.
+    let location_path = loop {
+        let path: OwnedObjectPath = client.get_property("Location")?;
+        if path.as_str() != "/" {
+            break path;
+        }
+        if started.elapsed() > TIMEOUT {
+            return Err(zbus::Error::Failure(format!(
+                "no location within {} seconds",
+                TIMEOUT.as_secs()
+            )));
+        }
+        thread::sleep(Duration::from_millis(200));
+    };
+    let location = proxy(
+        connection,
+        location_path.as_str(),
+        "org.freedesktop.GeoClue2.Location",
+    )?;
+    let latitude: f64 = location.get_property("Latitude")?;
+    let longitude: f64 = location.get_property("Longitude")?;
+    Ok(format_coordinates(latitude, longitude, precision))
+}
+
+fn proxy<'a>(
+    connection: &Connection,
+    path: &'a str,
+    interface: &'a str,
+) -> zbus::Result<Proxy<'a>> {
+    proxy::Builder::new(connection)
+        .destination(SERVICE)?
+        .path(path)?
+        .interface(interface)?
+        .cache_properties(CacheProperties::No)
+        .build()
+}
+
+/// GeoClue2's accuracy levels: there's no point in asking for street level
+/// accuracy when the position is rounded to a few kilometers.
+fn accuracy_level(precision: u8) -> u32 {
+    match precision {
+        0 | 1 => 4, // City
+        2 => 5,     // Neighborhood
+        3 => 6,     // Street
+        _ => 8,     // Exact
+    }
+}
+
+fn format_coordinates(latitude: f64, longitude: f64, precision: u8) -> String {
+    let precision = usize::from(precision);
+    format!(
+        "{},{}",
+        format_rounded(latitude, precision),
+        format_rounded(longitude, precision)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::sync::{Arc, Mutex};
+    use zbus::blocking::connection::Builder;
+    use zbus::interface;
+    use zbus::zvariant::ObjectPath;
+
+    const CLIENT_PATH: &str = "/org/freedesktop/GeoClue2/Client/1";
+    const LOCATION_PATH: &str = "/org/freedesktop/GeoClue2/Location/1";
+
+    /// What the stub saw: the client's properties and method calls.
+    #[derive(Default)]
+    struct Calls {
+        desktop_id: String,
+        accuracy_level: u32,
+        methods: Vec<&'static str>,
+    }
+
+    struct StubManager;
+
+    #[interface(name = "org.freedesktop.GeoClue2.Manager")]
+    impl StubManager {
+        fn get_client(&self) -> ObjectPath<'static> {
+            ObjectPath::from_static_str_unchecked(CLIENT_PATH)
+        }
+    }
+
+    struct StubClient(Arc<Mutex<Calls>>);
+
+    #[interface(name = "org.freedesktop.GeoClue2.Client")]
+    impl StubClient {
+        fn start(&self) {
+            self.0.lock().unwrap().methods.push("Start");
+        }
+
+        fn stop(&self) {
+            self.0.lock().unwrap().methods.push("Stop");
+        }
+
+        /// `/` until started, like GeoClue2 before it has a fix.
+        #[zbus(property)]
+        fn location(&self) -> ObjectPath<'static> {
+            let started = self.0.lock().unwrap().methods.contains(&"Start");
+            ObjectPath::from_static_str_unchecked(if started { LOCATION_PATH } else { "/" })
+        }
+
+        #[zbus(property)]
+        fn desktop_id(&self) -> String {
+            self.0.lock().unwrap().desktop_id.clone()
+        }
+
+        #[zbus(property)]
+        fn set_desktop_id(&self, desktop_id: String) {
+            self.0.lock().unwrap().desktop_id = desktop_id;
+        }
+
+        #[zbus(property)]
+        fn requested_accuracy_level(&self) -> u32 {
+            self.0.lock().unwrap().accuracy_level
+        }
+
+        #[zbus(property)]
+        fn set_requested_accuracy_level(&self, level: u32) {
+            self.0.lock().unwrap().accuracy_level = level;
+        }
+    }
+
+    struct StubLocation;
+
+    #[interface(name = "org.freedesktop.GeoClue2.Location")]
+    impl StubLocation {
+        #[zbus(property)]
+        fn latitude(&self) -> f64 {
+            52.373_08
+        }
+
+        #[zbus(property)]
+        fn longitude(&self) -> f64 {
+            -0.000_4
+        }
+    }
+
+    #[test]
+    // `unix_stream` only warns because a tokio build would need another type,
+    // which doesn't apply here.
+    #[allow(deprecated)]
+    fn asks_geoclue_for_the_position() {
+        let (server, client) = UnixStream::pair().unwrap();
+        let calls = Arc::new(Mutex::new(Calls::default()));
+        let stub_calls = calls.clone();
+        // Both ends have to take part in the handshake.
+        let stub = thread::spawn(move || {
+            Builder::unix_stream(server)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .serve_at("/org/freedesktop/GeoClue2/Manager", StubManager)
+                .unwrap()
+                .serve_at(CLIENT_PATH, StubClient(stub_calls))
+                .unwrap()
+                .serve_at(LOCATION_PATH, StubLocation)
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        let connection = Builder::unix_stream(client).p2p().build().unwrap();
+        let _stub = stub.join().unwrap();
+
+        assert_eq!(locate_on(&connection, 3).unwrap(), "52.373,0.000");
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.desktop_id, "wttrbar");
+        assert_eq!(calls.accuracy_level, 6);
+        assert_eq!(calls.methods, ["Start", "Stop"]);
+    }
+
+    #[test]
+    fn rounds_coordinates() {
+        assert_eq!(format_coordinates(52.37403, 4.88969, 2), "52.37,4.89");
+        assert_eq!(format_coordinates(-33.92487, 18.42406, 1), "-33.9,18.4");
+        assert_eq!(format_coordinates(51.47789, -0.0014, 0), "51,0");
+        assert_eq!(accuracy_level(1), 4);
+    }
+}
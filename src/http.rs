@@ -0,0 +1,236 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+use crate::cli::Args;
+use crate::error::{Error, NetworkError};
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(8);
+
+/// An HTTP client with timeouts and a bounded retry policy, so a flaky
+/// network (e.g. right after resume from suspend) delays the output by at
+/// most `--retry-max-time` instead of stalling Waybar.
+pub struct Http {
+    client: Client,
+    retry_max_time: Duration,
+}
+
+impl Http {
+    pub fn new(args: &Args) -> Self {
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(args.connect_timeout))
+            .timeout(Duration::from_secs(args.timeout))
+            .build()
+            .expect("Unable to create HTTP client");
+        Http {
+            client,
+            retry_max_time: Duration::from_secs(args.retry_max_time),
+        }
+    }
+
+    /// GET `url` and parse the body as JSON. Temporary failures are retried
+    /// with jittered exponential backoff until `--retry-max-time` is used up.
+    pub fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        let started = Instant::now();
+        let mut attempt = 0;
+        loop {
+            let err = match self.get_json_once(url) {
+                Ok(json) => return Ok(json),
+                Err(err) => err,
+            };
+            if !err.is_temporary() {
+                return Err(err);
+            }
+
+            let delay = retry_delay(attempt, fastrand::f64());
+            if started.elapsed() + delay > self.retry_max_time {
+                return Err(err);
+            }
+            thread::sleep(delay);
+            attempt += 1;
+        }
+    }
+
+    fn get_json_once<T: DeserializeOwned>(&self, url: &str) -> Result<T, Error> {
+        let response = self.client.get(url).send().map_err(classify)?;
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(NetworkError::RateLimited.into());
+        }
+        if status.is_server_error() {
+            return Err(NetworkError::Server(status.as_u16()).into());
+        }
+
+        let body = response.text().map_err(classify)?;
+        // wttr.in answers unknown locations with a plain text page. The
+        // provider fills in the location. A forecast may mention the phrase
+        // too, so only bodies that aren't JSON are checked.
+        let unknown_location = || body.contains("Unknown location");
+        if !status.is_success() {
+            if unknown_location() {
+                return Err(Error::UnknownLocation(String::new()));
+            }
+            return Err(NetworkError::Status(status.as_u16()).into());
+        }
+        serde_json::from_str(&body).map_err(|_| {
+            if unknown_location() {
+                Error::UnknownLocation(String::new())
+            } else {
+                Error::Parse
+            }
+        })
+    }
+}
+
+/// How long to wait before retry number `attempt` (counting from 0): the
+/// backoff doubles up to [`MAX_BACKOFF`], and `jitter` (between 0 and 1)
+/// picks somewhere between half and all of it.
+fn retry_delay(attempt: u32, jitter: f64) -> Duration {
+    let backoff = INITIAL_BACKOFF
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(MAX_BACKOFF);
+    let half = backoff / 2;
+    half + half.mul_f64(jitter.clamp(0.0, 1.0))
+}
+
+/// Tell DNS and TLS failures apart from other connection problems. reqwest
+/// only exposes them through the messages of the underlying errors.
+fn classify(err: reqwest::Error) -> NetworkError {
+    if err.is_timeout() {
+        return NetworkError::Timeout;
+    }
+
+    let mut messages = vec![];
+    let mut source: Option<&dyn std::error::Error> = Some(&err);
+    while let Some(err) = source {
+        messages.push(err.to_string().to_lowercase());
+        source = err.source();
+    }
+    let mentions = |needle: &str| messages.iter().any(|message| message.contains(needle));
+
+    if mentions("dns error") || mentions("failed to lookup address") {
+        NetworkError::Dns
+    } else if ["certificate", "tls", "ssl", "handshake"]
+        .iter()
+        .any(|needle| mentions(needle))
+    {
+        NetworkError::Tls
+    } else if mentions("timed out") {
+        NetworkError::Timeout
+    } else {
+        NetworkError::Unreachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_maximum() {
+        assert_eq!(retry_delay(0, 0.0), Duration::from_millis(250));
+        assert_eq!(retry_delay(0, 1.0), Duration::from_millis(500));
+        assert_eq!(retry_delay(2, 1.0), Duration::from_secs(2));
+        assert_eq!(retry_delay(4, 1.0), MAX_BACKOFF);
+        assert_eq!(retry_delay(40, 0.0), MAX_BACKOFF / 2);
+        assert_eq!(retry_delay(u32::MAX, 1.0), MAX_BACKOFF);
+    }
+
+    /// Answer one request on a local port with `response`.
+    fn serve(response: String) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let _ = stream.write_all(response.as_bytes());
+        });
+        url
+    }
+
+    fn get(url: &str) -> Result<serde_json::Value, Error> {
+        let args = Args::parse_from(["wttrbar", "--retry-max-time", "0"]);
+        Http::new(&args).get_json(url)
+    }
+
+    #[test]
+    fn maps_responses_to_errors() {
+        let response = |status: &str, body: &str| {
+            format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+        };
+
+        assert!(get(&serve(response("200 OK", "{\"ok\": 1}"))).is_ok());
+        assert!(matches!(
+            get(&serve(response("503 Service Unavailable", ""))),
+            Err(Error::Network(NetworkError::Server(503)))
+        ));
+        assert!(matches!(
+            get(&serve(response("429 Too Many Requests", ""))),
+            Err(Error::Network(NetworkError::RateLimited))
+        ));
+        assert!(matches!(
+            get(&serve(response(
+                "404 Not Found",
+                "Unknown location; please try ~Paris"
+            ))),
+            Err(Error::UnknownLocation(_))
+        ));
+        assert!(matches!(
+            get(&serve(response(
+                "200 OK",
+                "Unknown location; please try ~Paris"
+            ))),
+            Err(Error::UnknownLocation(_))
+        ));
+        assert!(get(&serve(response(
+            "200 OK",
+            "{\"weatherDesc\": \"Unknown location\"}"
+        )))
+        .is_ok());
+        assert!(matches!(
+            get(&serve(response("404 Not Found", "nothing here"))),
+            Err(Error::Network(NetworkError::Status(404)))
+        ));
+        assert!(matches!(
+            get(&serve(response("200 OK", "<html>"))),
+            Err(Error::Parse)
+        ));
+    }
+
+    #[test]
+    fn classifies_connection_failures() {
+        // Nothing listens on a port that was just released.
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        assert!(matches!(
+            get(&format!("http://127.0.0.1:{}/", port)),
+            Err(Error::Network(NetworkError::Unreachable))
+        ));
+        // The .invalid top level domain never resolves.
+        assert!(matches!(
+            get("http://wttrbar.invalid/"),
+            Err(Error::Network(NetworkError::Dns))
+        ));
+        // A plain HTTP server doesn't speak TLS.
+        assert!(matches!(
+            get(&serve("HTTP/1.1 200 OK\r\n\r\n".to_string()).replace("http:", "https:")),
+            Err(Error::Network(NetworkError::Tls))
+        ));
+    }
+}
@@ -1,5 +1,7 @@
 use clap::ValueEnum;
 
+use crate::model::Described;
+
 #[derive(Debug, Clone, ValueEnum)]
 pub enum Lang {
     EN,
@@ -45,6 +47,30 @@ impl Lang {
             Self::GA => "ga.wttr.in".to_string(),
         }
     }
+    /// Language code understood by wttr.in's `lang=` query parameter, used
+    /// when a custom base URL doesn't provide per-language subdomains.
+    pub fn code(&self) -> String {
+        match &self {
+            Self::EN => "en".to_string(),
+            Self::DE => "de".to_string(),
+            Self::PL => "pl".to_string(),
+            Self::RU => "ru".to_string(),
+            Self::TR => "tr".to_string(),
+            Self::FR => "fr".to_string(),
+            Self::BE => "be".to_string(),
+            Self::ZH => "zh".to_string(),
+            Self::ES => "es".to_string(),
+            Self::PT => "pt".to_string(),
+            Self::IT => "it".to_string(),
+            Self::JA => "ja".to_string(),
+            Self::UK => "uk".to_string(),
+            Self::SV => "sv".to_string(),
+            Self::DA => "da".to_string(),
+            Self::CS => "cs".to_string(),
+            Self::SK => "sk".to_string(),
+            Self::GA => "ga".to_string(),
+        }
+    }
     pub fn observation_time(&self) -> String {
         match &self {
             Self::EN => "Observed at".to_string(),
@@ -67,6 +93,28 @@ impl Lang {
             Self::GA => "Feicthe ag".to_string(),
         }
     }
+    pub fn as_of(&self) -> String {
+        match &self {
+            Self::EN => "As of".to_string(),
+            Self::DE => "Stand".to_string(),
+            Self::PL => "Stan na".to_string(),
+            Self::RU => "По состоянию на".to_string(),
+            Self::TR => "Son güncelleme".to_string(),
+            Self::FR => "Données de".to_string(),
+            Self::BE => "Па стане на".to_string(),
+            Self::ZH => "数据时间".to_string(),
+            Self::ES => "Datos de las".to_string(),
+            Self::PT => "Dados das".to_string(),
+            Self::IT => "Dati delle".to_string(),
+            Self::JA => "取得時刻".to_string(),
+            Self::UK => "Станом на".to_string(),
+            Self::SV => "Data från".to_string(),
+            Self::DA => "Data fra".to_string(),
+            Self::CS => "Stav k".to_string(),
+            Self::SK => "Stav k".to_string(),
+            Self::GA => "Sonraí ó".to_string(),
+        }
+    }
     pub fn feels_like(&self) -> String {
         match &self {
             Self::EN => "Feels Like".to_string(),
@@ -353,6 +401,380 @@ impl Lang {
             Self::GA => "Gaoth".to_string(),
         }
     }
+    pub fn pressure(&self) -> String {
+        match &self {
+            Self::EN => "Pressure".to_string(),
+            Self::DE => "Luftdruck".to_string(),
+            Self::PL => "Ciśnienie".to_string(),
+            Self::RU => "Давление".to_string(),
+            Self::TR => "Basınç".to_string(),
+            Self::FR => "Pression".to_string(),
+            Self::BE => "Ціск".to_string(),
+            Self::ZH => "气压".to_string(),
+            Self::ES => "Presión".to_string(),
+            Self::PT => "Pressão".to_string(),
+            Self::IT => "Pressione".to_string(),
+            Self::JA => "気圧".to_string(),
+            Self::UK => "Тиск".to_string(),
+            Self::SV => "Lufttryck".to_string(),
+            Self::DA => "Lufttryk".to_string(),
+            Self::CS => "Tlak".to_string(),
+            Self::SK => "Tlak".to_string(),
+            Self::GA => "Brú".to_string(),
+        }
+    }
+    pub fn uv_index(&self) -> String {
+        match &self {
+            Self::EN => "UV Index".to_string(),
+            Self::DE => "UV-Index".to_string(),
+            Self::PL => "Indeks UV".to_string(),
+            Self::RU => "УФ-индекс".to_string(),
+            Self::TR => "UV indeksi".to_string(),
+            Self::FR => "Indice UV".to_string(),
+            Self::BE => "УФ-індэкс".to_string(),
+            Self::ZH => "紫外线指数".to_string(),
+            Self::ES => "Índice UV".to_string(),
+            Self::PT => "Índice UV".to_string(),
+            Self::IT => "Indice UV".to_string(),
+            Self::JA => "UV指数".to_string(),
+            Self::UK => "УФ-індекс".to_string(),
+            Self::SV => "UV-index".to_string(),
+            Self::DA => "UV-indeks".to_string(),
+            Self::CS => "UV index".to_string(),
+            Self::SK => "UV index".to_string(),
+            Self::GA => "Innéacs UV".to_string(),
+        }
+    }
+    pub fn visibility(&self) -> String {
+        match &self {
+            Self::EN => "Visibility".to_string(),
+            Self::DE => "Sichtweite".to_string(),
+            Self::PL => "Widoczność".to_string(),
+            Self::RU => "Видимость".to_string(),
+            Self::TR => "Görüş mesafesi".to_string(),
+            Self::FR => "Visibilité".to_string(),
+            Self::BE => "Бачнасць".to_string(),
+            Self::ZH => "能见度".to_string(),
+            Self::ES => "Visibilidad".to_string(),
+            Self::PT => "Visibilidade".to_string(),
+            Self::IT => "Visibilità".to_string(),
+            Self::JA => "視程".to_string(),
+            Self::UK => "Видимість".to_string(),
+            Self::SV => "Sikt".to_string(),
+            Self::DA => "Sigtbarhed".to_string(),
+            Self::CS => "Viditelnost".to_string(),
+            Self::SK => "Viditeľnosť".to_string(),
+            Self::GA => "Léargas".to_string(),
+        }
+    }
+    pub fn precipitation(&self) -> String {
+        match &self {
+            Self::EN => "Precipitation".to_string(),
+            Self::DE => "Niederschlag".to_string(),
+            Self::PL => "Opady".to_string(),
+            Self::RU => "Осадки".to_string(),
+            Self::TR => "Yağış".to_string(),
+            Self::FR => "Précipitations".to_string(),
+            Self::BE => "Ападкі".to_string(),
+            Self::ZH => "降水量".to_string(),
+            Self::ES => "Precipitación".to_string(),
+            Self::PT => "Precipitação".to_string(),
+            Self::IT => "Precipitazioni".to_string(),
+            Self::JA => "降水量".to_string(),
+            Self::UK => "Опади".to_string(),
+            Self::SV => "Nederbörd".to_string(),
+            Self::DA => "Nedbør".to_string(),
+            Self::CS => "Srážky".to_string(),
+            Self::SK => "Zrážky".to_string(),
+            Self::GA => "Deascadh".to_string(),
+        }
+    }
+    pub fn cloud_cover(&self) -> String {
+        match &self {
+            Self::EN => "Cloud Cover".to_string(),
+            Self::DE => "Bewölkung".to_string(),
+            Self::PL => "Zachmurzenie".to_string(),
+            Self::RU => "Облачность".to_string(),
+            Self::TR => "Bulutluluk".to_string(),
+            Self::FR => "Couverture nuageuse".to_string(),
+            Self::BE => "Воблачнасць".to_string(),
+            Self::ZH => "云量".to_string(),
+            Self::ES => "Nubosidad".to_string(),
+            Self::PT => "Nebulosidade".to_string(),
+            Self::IT => "Copertura nuvolosa".to_string(),
+            Self::JA => "雲量".to_string(),
+            Self::UK => "Хмарність".to_string(),
+            Self::SV => "Molnighet".to_string(),
+            Self::DA => "Skydække".to_string(),
+            Self::CS => "Oblačnost".to_string(),
+            Self::SK => "Oblačnosť".to_string(),
+            Self::GA => "Clúdach scamall".to_string(),
+        }
+    }
+    pub fn wind_direction(&self) -> String {
+        match &self {
+            Self::EN => "Wind Direction".to_string(),
+            Self::DE => "Windrichtung".to_string(),
+            Self::PL => "Kierunek wiatru".to_string(),
+            Self::RU => "Направление ветра".to_string(),
+            Self::TR => "Rüzgar yönü".to_string(),
+            Self::FR => "Direction du vent".to_string(),
+            Self::BE => "Кірунак ветру".to_string(),
+            Self::ZH => "风向".to_string(),
+            Self::ES => "Dirección del viento".to_string(),
+            Self::PT => "Direção do vento".to_string(),
+            Self::IT => "Direzione del vento".to_string(),
+            Self::JA => "風向".to_string(),
+            Self::UK => "Напрямок вітру".to_string(),
+            Self::SV => "Vindriktning".to_string(),
+            Self::DA => "Vindretning".to_string(),
+            Self::CS => "Směr větru".to_string(),
+            Self::SK => "Smer vetra".to_string(),
+            Self::GA => "Treo na gaoithe".to_string(),
+        }
+    }
+    pub fn alerts(&self) -> String {
+        match &self {
+            Self::EN => "Alerts".to_string(),
+            Self::DE => "Warnungen".to_string(),
+            Self::PL => "Ostrzeżenia".to_string(),
+            Self::RU => "Предупреждения".to_string(),
+            Self::TR => "Uyarılar".to_string(),
+            Self::FR => "Alertes".to_string(),
+            Self::BE => "Папярэджанні".to_string(),
+            Self::ZH => "预警".to_string(),
+            Self::ES => "Alertas".to_string(),
+            Self::PT => "Alertas".to_string(),
+            Self::IT => "Avvisi".to_string(),
+            Self::JA => "警報".to_string(),
+            Self::UK => "Попередження".to_string(),
+            Self::SV => "Varningar".to_string(),
+            Self::DA => "Advarsler".to_string(),
+            Self::CS => "Výstrahy".to_string(),
+            Self::SK => "Výstrahy".to_string(),
+            Self::GA => "Rabhaidh".to_string(),
+        }
+    }
+    pub fn rain_soon(&self) -> String {
+        match &self {
+            Self::EN => "Rain expected within the hour".to_string(),
+            Self::DE => "Regen innerhalb der nächsten Stunde erwartet".to_string(),
+            Self::PL => "Deszcz w ciągu najbliższej godziny".to_string(),
+            Self::RU => "Дождь в течение часа".to_string(),
+            Self::TR => "Bir saat içinde yağmur bekleniyor".to_string(),
+            Self::FR => "Pluie attendue dans l'heure".to_string(),
+            Self::BE => "Дождж на працягу гадзіны".to_string(),
+            Self::ZH => "一小时内有雨".to_string(),
+            Self::ES => "Lluvia prevista en la próxima hora".to_string(),
+            Self::PT => "Chuva prevista na próxima hora".to_string(),
+            Self::IT => "Pioggia prevista entro un'ora".to_string(),
+            Self::JA => "1時間以内に雨の予報".to_string(),
+            Self::UK => "Дощ протягом години".to_string(),
+            Self::SV => "Regn väntas inom en timme".to_string(),
+            Self::DA => "Regn ventes inden for en time".to_string(),
+            Self::CS => "Déšť do hodiny".to_string(),
+            Self::SK => "Dážď do hodiny".to_string(),
+            Self::GA => "Báisteach laistigh d'uair an chloig".to_string(),
+        }
+    }
+    pub fn snow_soon(&self) -> String {
+        match &self {
+            Self::EN => "Snow expected within the hour".to_string(),
+            Self::DE => "Schnee innerhalb der nächsten Stunde erwartet".to_string(),
+            Self::PL => "Śnieg w ciągu najbliższej godziny".to_string(),
+            Self::RU => "Снег в течение часа".to_string(),
+            Self::TR => "Bir saat içinde kar bekleniyor".to_string(),
+            Self::FR => "Neige attendue dans l'heure".to_string(),
+            Self::BE => "Снег на працягу гадзіны".to_string(),
+            Self::ZH => "一小时内有雪".to_string(),
+            Self::ES => "Nieve prevista en la próxima hora".to_string(),
+            Self::PT => "Neve prevista na próxima hora".to_string(),
+            Self::IT => "Neve prevista entro un'ora".to_string(),
+            Self::JA => "1時間以内に雪の予報".to_string(),
+            Self::UK => "Сніг протягом години".to_string(),
+            Self::SV => "Snö väntas inom en timme".to_string(),
+            Self::DA => "Sne ventes inden for en time".to_string(),
+            Self::CS => "Sníh do hodiny".to_string(),
+            Self::SK => "Sneh do hodiny".to_string(),
+            Self::GA => "Sneachta laistigh d'uair an chloig".to_string(),
+        }
+    }
+    pub fn location_mismatch(&self) -> String {
+        match &self {
+            Self::EN => "Location mismatch".to_string(),
+            Self::DE => "Ort weicht ab".to_string(),
+            Self::PL => "Niezgodna lokalizacja".to_string(),
+            Self::RU => "Местоположение не совпадает".to_string(),
+            Self::TR => "Konum uyuşmuyor".to_string(),
+            Self::FR => "Lieu différent".to_string(),
+            Self::BE => "Месца не супадае".to_string(),
+            Self::ZH => "位置不匹配".to_string(),
+            Self::ES => "La ubicación no coincide".to_string(),
+            Self::PT => "Localização diferente".to_string(),
+            Self::IT => "Località diversa".to_string(),
+            Self::JA => "場所の不一致".to_string(),
+            Self::UK => "Місце не збігається".to_string(),
+            Self::SV => "Platsen stämmer inte".to_string(),
+            Self::DA => "Stedet passer ikke".to_string(),
+            Self::CS => "Místo nesouhlasí".to_string(),
+            Self::SK => "Miesto nesúhlasí".to_string(),
+            Self::GA => "Ní hionann an áit".to_string(),
+        }
+    }
+    pub fn error_network(&self) -> String {
+        match &self {
+            Self::EN => "Cannot reach the weather service".to_string(),
+            Self::DE => "Wetterdienst nicht erreichbar".to_string(),
+            Self::PL => "Brak połączenia z serwisem pogodowym".to_string(),
+            Self::RU => "Сервис погоды недоступен".to_string(),
+            Self::TR => "Hava durumu servisine ulaşılamıyor".to_string(),
+            Self::FR => "Service météo injoignable".to_string(),
+            Self::BE => "Сэрвіс надвор'я недаступны".to_string(),
+            Self::ZH => "无法连接天气服务".to_string(),
+            Self::ES => "No se puede acceder al servicio meteorológico".to_string(),
+            Self::PT => "Não é possível aceder ao serviço meteorológico".to_string(),
+            Self::IT => "Servizio meteo non raggiungibile".to_string(),
+            Self::JA => "天気サービスに接続できません".to_string(),
+            Self::UK => "Сервіс погоди недоступний".to_string(),
+            Self::SV => "Vädertjänsten kan inte nås".to_string(),
+            Self::DA => "Vejrtjenesten kan ikke nås".to_string(),
+            Self::CS => "Meteorologická služba je nedostupná".to_string(),
+            Self::SK => "Meteorologická služba je nedostupná".to_string(),
+            Self::GA => "Ní féidir an tseirbhís aimsire a bhaint amach".to_string(),
+        }
+    }
+    pub fn error_parse(&self) -> String {
+        match &self {
+            Self::EN => "Invalid response from the weather service".to_string(),
+            Self::DE => "Ungültige Antwort vom Wetterdienst".to_string(),
+            Self::PL => "Nieprawidłowa odpowiedź serwisu pogodowego".to_string(),
+            Self::RU => "Некорректный ответ сервиса погоды".to_string(),
+            Self::TR => "Hava durumu servisinden geçersiz yanıt".to_string(),
+            Self::FR => "Réponse invalide du service météo".to_string(),
+            Self::BE => "Няправільны адказ сэрвісу надвор'я".to_string(),
+            Self::ZH => "天气服务返回了无效的数据".to_string(),
+            Self::ES => "Respuesta no válida del servicio meteorológico".to_string(),
+            Self::PT => "Resposta inválida do serviço meteorológico".to_string(),
+            Self::IT => "Risposta non valida dal servizio meteo".to_string(),
+            Self::JA => "天気サービスからの応答が無効です".to_string(),
+            Self::UK => "Некоректна відповідь сервісу погоди".to_string(),
+            Self::SV => "Ogiltigt svar från vädertjänsten".to_string(),
+            Self::DA => "Ugyldigt svar fra vejrtjenesten".to_string(),
+            Self::CS => "Neplatná odpověď meteorologické služby".to_string(),
+            Self::SK => "Neplatná odpoveď meteorologickej služby".to_string(),
+            Self::GA => "Freagra neamhbhailí ón tseirbhís aimsire".to_string(),
+        }
+    }
+    pub fn error_unknown_location(&self) -> String {
+        match &self {
+            Self::EN => "Unknown location".to_string(),
+            Self::DE => "Unbekannter Ort".to_string(),
+            Self::PL => "Nieznana lokalizacja".to_string(),
+            Self::RU => "Неизвестное местоположение".to_string(),
+            Self::TR => "Bilinmeyen konum".to_string(),
+            Self::FR => "Lieu inconnu".to_string(),
+            Self::BE => "Невядомае месцазнаходжанне".to_string(),
+            Self::ZH => "未知地点".to_string(),
+            Self::ES => "Ubicación desconocida".to_string(),
+            Self::PT => "Localização desconhecida".to_string(),
+            Self::IT => "Posizione sconosciuta".to_string(),
+            Self::JA => "不明な地点".to_string(),
+            Self::UK => "Невідоме розташування".to_string(),
+            Self::SV => "Okänd plats".to_string(),
+            Self::DA => "Ukendt placering".to_string(),
+            Self::CS => "Neznámá lokalita".to_string(),
+            Self::SK => "Neznáma lokalita".to_string(),
+            Self::GA => "Láthair anaithnid".to_string(),
+        }
+    }
+    pub fn error_missing_location(&self) -> String {
+        match &self {
+            Self::EN => "No location configured".to_string(),
+            Self::DE => "Kein Ort angegeben".to_string(),
+            Self::PL => "Nie podano lokalizacji".to_string(),
+            Self::RU => "Местоположение не указано".to_string(),
+            Self::TR => "Konum belirtilmedi".to_string(),
+            Self::FR => "Aucun lieu indiqué".to_string(),
+            Self::BE => "Месцазнаходжанне не пазначана".to_string(),
+            Self::ZH => "未设置地点".to_string(),
+            Self::ES => "No se ha indicado ninguna ubicación".to_string(),
+            Self::PT => "Nenhuma localização indicada".to_string(),
+            Self::IT => "Nessuna posizione indicata".to_string(),
+            Self::JA => "地点が設定されていません".to_string(),
+            Self::UK => "Розташування не вказано".to_string(),
+            Self::SV => "Ingen plats angiven".to_string(),
+            Self::DA => "Ingen placering angivet".to_string(),
+            Self::CS => "Není zadána lokalita".to_string(),
+            Self::SK => "Nie je zadaná lokalita".to_string(),
+            Self::GA => "Níor sonraíodh láthair".to_string(),
+        }
+    }
+    pub fn error_missing_field(&self) -> String {
+        match &self {
+            Self::EN => "Incomplete weather data".to_string(),
+            Self::DE => "Unvollständige Wetterdaten".to_string(),
+            Self::PL => "Niekompletne dane pogodowe".to_string(),
+            Self::RU => "Неполные данные о погоде".to_string(),
+            Self::TR => "Eksik hava durumu verisi".to_string(),
+            Self::FR => "Données météo incomplètes".to_string(),
+            Self::BE => "Няпоўныя даныя пра надвор'е".to_string(),
+            Self::ZH => "天气数据不完整".to_string(),
+            Self::ES => "Datos meteorológicos incompletos".to_string(),
+            Self::PT => "Dados meteorológicos incompletos".to_string(),
+            Self::IT => "Dati meteo incompleti".to_string(),
+            Self::JA => "天気データが不完全です".to_string(),
+            Self::UK => "Неповні дані про погоду".to_string(),
+            Self::SV => "Ofullständiga väderdata".to_string(),
+            Self::DA => "Ufuldstændige vejrdata".to_string(),
+            Self::CS => "Neúplná data o počasí".to_string(),
+            Self::SK => "Neúplné údaje o počasí".to_string(),
+            Self::GA => "Sonraí aimsire neamhiomlána".to_string(),
+        }
+    }
+    pub fn error_cache(&self) -> String {
+        match &self {
+            Self::EN => "Cannot write the weather cache".to_string(),
+            Self::DE => "Wetter-Cache kann nicht geschrieben werden".to_string(),
+            Self::PL => "Nie można zapisać pamięci podręcznej".to_string(),
+            Self::RU => "Не удалось записать кэш погоды".to_string(),
+            Self::TR => "Hava durumu önbelleği yazılamıyor".to_string(),
+            Self::FR => "Impossible d'écrire le cache météo".to_string(),
+            Self::BE => "Не ўдалося запісаць кэш надвор'я".to_string(),
+            Self::ZH => "无法写入天气缓存".to_string(),
+            Self::ES => "No se puede escribir la caché del tiempo".to_string(),
+            Self::PT => "Não é possível gravar a cache meteorológica".to_string(),
+            Self::IT => "Impossibile scrivere la cache meteo".to_string(),
+            Self::JA => "天気キャッシュを書き込めません".to_string(),
+            Self::UK => "Не вдалося записати кеш погоди".to_string(),
+            Self::SV => "Kan inte skriva väder-cachen".to_string(),
+            Self::DA => "Kan ikke skrive vejr-cachen".to_string(),
+            Self::CS => "Nelze zapsat mezipaměť počasí".to_string(),
+            Self::SK => "Nedá sa zapísať vyrovnávacia pamäť počasia".to_string(),
+            Self::GA => "Ní féidir taisce na haimsire a scríobh".to_string(),
+        }
+    }
+    pub fn error_config(&self) -> String {
+        match &self {
+            Self::EN => "Invalid configuration".to_string(),
+            Self::DE => "Ungültige Konfiguration".to_string(),
+            Self::PL => "Nieprawidłowa konfiguracja".to_string(),
+            Self::RU => "Неверная конфигурация".to_string(),
+            Self::TR => "Geçersiz yapılandırma".to_string(),
+            Self::FR => "Configuration invalide".to_string(),
+            Self::BE => "Няправільная канфігурацыя".to_string(),
+            Self::ZH => "配置无效".to_string(),
+            Self::ES => "Configuración no válida".to_string(),
+            Self::PT => "Configuração inválida".to_string(),
+            Self::IT => "Configurazione non valida".to_string(),
+            Self::JA => "設定が無効です".to_string(),
+            Self::UK => "Неправильна конфігурація".to_string(),
+            Self::SV => "Ogiltig konfiguration".to_string(),
+            Self::DA => "Ugyldig konfiguration".to_string(),
+            Self::CS => "Neplatná konfigurace".to_string(),
+            Self::SK => "Neplatná konfigurácia".to_string(),
+            Self::GA => "Cumraíocht neamhbhailí".to_string(),
+        }
+    }
     /// Candidate keys (in priority order) under which wttr.in may expose the
     /// localized weather description for this language. wttr.in currently
     /// returns it under the literal key `lang_xx` for every non-English
@@ -383,14 +805,12 @@ impl Lang {
     }
 
     /// Extract the localized weather description value from a `current_condition`
-    /// or `hourly` JSON object, trying each candidate key in order.
-    pub fn weather_desc_value<'a>(&self, node: &'a serde_json::Value) -> Option<&'a str> {
-        for key in self.weather_desc_keys() {
-            if let Some(s) = node[*key][0]["value"].as_str() {
-                return Some(s);
-            }
-        }
-        None
+    /// or `hourly` entry, trying each candidate key in order.
+    pub fn weather_desc_value(&self, node: &impl Described) -> Option<String> {
+        self.weather_desc_keys()
+            .iter()
+            .find_map(|key| node.description(key))
+            .map(str::to_string)
     }
 
     pub fn locale_str(&self) -> String {
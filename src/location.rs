@@ -0,0 +1,322 @@
+use crate::cli::Args;
+use crate::format::percent_encode;
+use crate::geoclue::AUTO_GEOCLUE;
+use crate::model::{first_text, Area};
+
+/// How alike two words have to be (1 being equal) to count as the same name,
+/// so spelling variants like `Koeln` and `Koln` don't raise a mismatch.
+const NAME_SIMILARITY: f64 = 0.75;
+
+/// The forms of `--location` wttr.in understands.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LocationKind {
+    /// No location, the provider guesses it from the IP address, or
+    /// `auto-geoclue`, which asks GeoClue2 on every fetch.
+    Auto,
+    /// A city or any other place name, like `Sao Paulo`.
+    Name,
+    /// `~` and a landmark to search for, like `~Eiffel Tower`.
+    Landmark,
+    /// A three letter airport code, like `muc`.
+    Airport,
+    /// `@` and a domain name, located by its server, like `@github.com`.
+    Domain,
+    /// `lat,lon`, like `52.37,4.89`.
+    Coordinates,
+    /// A postal code, like `10115` or `SW1A 1AA`.
+    PostalCode,
+}
+
+impl LocationKind {
+    /// Tell the form of a location apart, without checking it.
+    pub fn of(location: &str) -> Self {
+        let location = location.trim();
+        if location.is_empty() || location == AUTO_GEOCLUE {
+            Self::Auto
+        } else if location.starts_with('~') {
+            Self::Landmark
+        } else if location.starts_with('@') {
+            Self::Domain
+        } else if location
+            .split_once(',')
+            .is_some_and(|(lat, lon)| is_number(lat) && is_number(lon))
+        {
+            Self::Coordinates
+        } else if location.len() == 3 && location.chars().all(|c| c.is_ascii_alphabetic()) {
+            Self::Airport
+        } else if location.chars().any(|c| c.is_ascii_digit())
+            && location
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-')
+        {
+            Self::PostalCode
+        } else {
+            Self::Name
+        }
+    }
+}
+
+/// Check a location given with `--location` and tell its form.
+pub fn validate(location: &str) -> Result<LocationKind, String> {
+    if location.chars().any(char::is_control) {
+        return Err(format!("{:?} contains control characters", location));
+    }
+    let kind = LocationKind::of(location);
+    let location = location.trim();
+    match kind {
+        LocationKind::Landmark if location[1..].trim().is_empty() => Err(format!(
+            "{:?} needs a landmark after the ~, like ~Eiffel Tower",
+            location
+        )),
+        LocationKind::Domain => {
+            let domain = &location[1..];
+            let valid = domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && domain
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+            if valid {
+                Ok(kind)
+            } else {
+                Err(format!(
+                    "{:?} needs a domain name after the @, like @github.com",
+                    location
+                ))
+            }
+        }
+        LocationKind::Coordinates => {
+            let (lat, lon) = location.split_once(',').unwrap_or_default();
+            let (lat, lon): (f64, f64) = (
+                lat.trim().parse().unwrap_or_default(),
+                lon.trim().parse().unwrap_or_default(),
+            );
+            if !(-90.0..=90.0).contains(&lat) {
+                Err(format!("latitude {} is not between -90 and 90", lat))
+            } else if !(-180.0..=180.0).contains(&lon) {
+                Err(format!("longitude {} is not between -180 and 180", lon))
+            } else {
+                Ok(kind)
+            }
+        }
+        // A lone `52.37` is half a coordinate, not a postal code.
+        LocationKind::Name | LocationKind::PostalCode
+            if is_number(location) && !location.chars().all(|c| c.is_ascii_digit()) =>
+        {
+            Err(format!(
+                "{:?} looks like a coordinate, use lat,lon like 52.37,4.89",
+                location
+            ))
+        }
+        _ => Ok(kind),
+    }
+}
+
+/// Percent-encode a location for the path of a wttr.in URL. Spaces become
+/// `+`, which wttr.in reads as a space; the characters of its location forms
+/// (`~`, `@`, `,`) are kept.
+pub fn encode(location: &str) -> String {
+    percent_encode(&location.trim().replace(' ', "+"), |byte| {
+        byte.is_ascii_alphanumeric() || b"-_.~@,+".contains(&byte)
+    })
+}
+
+/// Whether the place the provider resolved `requested` to differs from what
+/// was asked for: another name, coordinates further away than
+/// `--location-tolerance`, or not in the `--country` or `--region`.
+pub fn is_mismatch(requested: &str, area: &Area, args: &Args) -> bool {
+    let resolved: Vec<&str> = [&area.area_name, &area.region, &area.country]
+        .into_iter()
+        .filter_map(|texts| first_text(texts))
+        .filter(|text| !text.is_empty())
+        .collect();
+    if resolved.is_empty() {
+        return false;
+    }
+    let pinned = [
+        (args.country.as_deref(), first_text(&area.country)),
+        (args.region.as_deref(), first_text(&area.region)),
+    ];
+    for (expected, actual) in pinned {
+        if let (Some(expected), Some(actual)) = (expected, actual) {
+            if !names_match(expected, &[actual]) {
+                return true;
+            }
+        }
+    }
+    match LocationKind::of(requested) {
+        LocationKind::Name => !names_match(requested, &resolved),
+        LocationKind::Coordinates => {
+            let parse = |value: &str| value.trim().parse::<f64>().ok();
+            let (lat, lon) = requested.split_once(',').unwrap_or_default();
+            let requested = parse(lat).zip(parse(lon));
+            let resolved = area
+                .latitude
+                .as_deref()
+                .and_then(parse)
+                .zip(area.longitude.as_deref().and_then(parse));
+            match (requested, resolved) {
+                (Some(requested), Some(resolved)) => {
+                    distance_km(requested, resolved) > args.location_tolerance
+                }
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Whether every word of `name` is one of the words in `places`.
+fn names_match(name: &str, places: &[&str]) -> bool {
+    let place_words: Vec<String> = places.iter().flat_map(|place| words(place)).collect();
+    words(name).iter().all(|word| {
+        place_words
+            .iter()
+            .any(|place_word| similarity(word, place_word) >= NAME_SIMILARITY)
+    })
+}
+
+/// Lowercase words without the accents of Latin letters.
+fn words(text: &str) -> Vec<String> {
+    let folded: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à'..='å' => "a".to_string(),
+            'ç' => "c".to_string(),
+            'è'..='ë' => "e".to_string(),
+            'ì'..='ï' => "i".to_string(),
+            'ñ' => "n".to_string(),
+            'ò'..='ö' | 'ø' => "o".to_string(),
+            'ù'..='ü' => "u".to_string(),
+            'ý' | 'ÿ' => "y".to_string(),
+            'ß' => "ss".to_string(),
+            c => c.to_string(),
+        })
+        .collect();
+    folded
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// 1 minus the edit distance relative to the longer word.
+fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// Great-circle distance between two `(lat, lon)` points.
+fn distance_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    const EARTH_RADIUS_KM: f64 = 6371.0;
+    let (lat_a, lat_b) = (a.0.to_radians(), b.0.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (b.1 - a.1).to_radians();
+    let h = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+}
+
+fn is_number(part: &str) -> bool {
+    part.trim().parse::<f64>().is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_location_forms_apart() {
+        assert_eq!(LocationKind::of(""), LocationKind::Auto);
+        assert_eq!(LocationKind::of("auto-geoclue"), LocationKind::Auto);
+        assert_eq!(LocationKind::of("New York"), LocationKind::Name);
+        assert_eq!(LocationKind::of("~Eiffel Tower"), LocationKind::Landmark);
+        assert_eq!(LocationKind::of("muc"), LocationKind::Airport);
+        assert_eq!(LocationKind::of("@github.com"), LocationKind::Domain);
+        assert_eq!(LocationKind::of("52.37,4.89"), LocationKind::Coordinates);
+        assert_eq!(LocationKind::of("10115"), LocationKind::PostalCode);
+        assert_eq!(LocationKind::of("SW1A 1AA"), LocationKind::PostalCode);
+    }
+
+    #[test]
+    fn rejects_malformed_locations() {
+        assert!(validate("~ ").is_err());
+        assert!(validate("@localhost").is_err());
+        assert!(validate("@git hub.com").is_err());
+        assert!(validate("95,4.89").is_err());
+        assert!(validate("52.37,190").is_err());
+        assert!(validate("52.37").is_err());
+        assert!(validate("Amsterdam\n").is_err());
+        assert_eq!(validate("São Paulo"), Ok(LocationKind::Name));
+    }
+
+    fn area(name: &str, region: &str, country: &str, lat: &str, lon: &str) -> Area {
+        serde_json::from_value(serde_json::json!({
+            "areaName": [{"value": name}],
+            "region": [{"value": region}],
+            "country": [{"value": country}],
+            "latitude": lat,
+            "longitude": lon,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn detects_locations_resolved_elsewhere() {
+        use clap::Parser;
+        let args = |cli: &[&str]| Args::parse_from([&["wttrbar"], cli].concat());
+        let paris_texas = area(
+            "Paris",
+            "Texas",
+            "United States of America",
+            "33.66",
+            "-95.56",
+        );
+        let paris = area("Paris", "Ile-de-France", "France", "48.867", "2.333");
+
+        assert!(!is_mismatch("Paris", &paris_texas, &args(&[])));
+        assert!(is_mismatch(
+            "Paris",
+            &paris_texas,
+            &args(&["--country", "France"])
+        ));
+        assert!(!is_mismatch(
+            "paris",
+            &paris,
+            &args(&["--country", "france"])
+        ));
+        assert!(!is_mismatch("Paris, Île-de-France", &paris, &args(&[])));
+        assert!(is_mismatch("Springfield", &paris, &args(&[])));
+
+        assert!(!is_mismatch("48.85,2.35", &paris, &args(&[])));
+        assert!(is_mismatch("48.85,2.35", &paris_texas, &args(&[])));
+        assert!(!is_mismatch(
+            "muc",
+            &paris_texas,
+            &args(&["--region", "Texas"])
+        ));
+    }
+
+    #[test]
+    fn encodes_locations() {
+        assert_eq!(encode("New York"), "New+York");
+        assert_eq!(encode("São Paulo"), "S%C3%A3o+Paulo");
+        assert_eq!(encode("~Eiffel Tower"), "~Eiffel+Tower");
+        assert_eq!(encode("what?#x"), "what%3F%23x");
+        assert_eq!(encode("@github.com"), "@github.com");
+        assert_eq!(encode("-33.9,18.4"), "-33.9,18.4");
+    }
+}
@@ -1,302 +1,116 @@
-use core::time;
-use std::collections::HashMap;
-use std::fs::{metadata, read_to_string, File};
-use std::io::Write;
-use std::process::exit;
-use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::Duration;
 
-use chrono::{Local, Locale, NaiveDate, Timelike};
-use clap::Parser;
-use reqwest::blocking::Client;
-use serde_json::{json, Value};
-
-use crate::cli::Args;
-use crate::constants::{WEATHER_CODES, WEATHER_CODES_NERD};
-use crate::format::{
-    format_ampm_time, format_chances, format_indicator, format_moon_phase_icon, format_temp,
-    format_time, get_observation_time,
-};
+use crate::cache::Cache;
+use crate::error::Error;
+use crate::http::Http;
 use crate::lang::Lang;
+use crate::render::{render, render_error, Layout};
+use crate::source::{collect_locations, load_all, Source};
 
+mod alert;
+mod cache;
 mod cli;
+mod config;
 mod constants;
+mod daemon;
+mod error;
+mod expr;
 mod format;
+mod geoclue;
+mod http;
 mod lang;
+mod location;
+mod model;
+mod notify;
+mod provider;
+mod render;
+mod source;
+mod template;
+mod timezone;
+mod units;
 
 fn main() {
-    let args = Args::parse();
-    let lang = if let Some(lang) = args.lang {
-        lang
-    } else {
-        Lang::EN
-    };
-
-    let mut data = HashMap::new();
-
-    let location = args.location.unwrap_or(String::new());
-    let weather_url = format!(
-        "https://{}/{}?format=j1",
-        lang.wttr_in_subdomain(),
-        location
-    );
-    let cachefile = format!(
-        "/tmp/wttrbar-{}-{}.json",
-        location,
-        lang.wttr_in_subdomain()
-    );
-
-    let mut iterations = 0;
-    let threshold = 20;
-
-    let is_cache_file_recent = if let Ok(metadata) = metadata(&cachefile) {
-        let ten_minutes_ago = SystemTime::now() - Duration::from_secs(600);
-        metadata
-            .modified()
-            .map_or(false, |mod_time| mod_time > ten_minutes_ago)
-    } else {
-        false
-    };
-
-    let client = Client::new();
-    let weather = if is_cache_file_recent {
-        let json_str = read_to_string(&cachefile).unwrap();
-        serde_json::from_str::<serde_json::Value>(&json_str).unwrap()
-    } else {
-        loop {
-            match client.get(&weather_url).send() {
-                Ok(response) => match response.json::<Value>() {
-                    Ok(json) => break json,
-                    Err(_) => {
-                        println!("{{\"text\":\"⛓️‍💥\", \"tooltip\":\"invalid wttr.in response\"}}");
-                        exit(0)
-                    }
-                },
-                Err(_) => {
-                    iterations += 1;
-                    thread::sleep(time::Duration::from_millis(500 * iterations));
-
-                    if iterations == threshold {
-                        println!("{{\"text\":\"⛓️‍💥\", \"tooltip\":\"cannot access wttr.in\"}}");
-                        exit(0)
-                    }
-                }
-            }
+    let args = match config::parse_args() {
+        Ok(args) => args,
+        Err(err) => {
+            println!("{}", render_error(&err, &Lang::EN, "wttrbar"));
+            return;
         }
     };
-
-    if !is_cache_file_recent {
-        let mut file = File::create(&cachefile)
-            .expect(format!("Unable to create cache file at {}", cachefile).as_str());
-
-        file.write_all(serde_json::to_string_pretty(&weather).unwrap().as_bytes())
-            .expect(format!("Unable to write cache file at {}", cachefile).as_str());
-    }
-    let current_condition = &weather["current_condition"][0];
-    let nearest_area = &weather["nearest_area"][0];
-    let feels_like = if args.fahrenheit {
-        current_condition["FeelsLikeF"].as_str().unwrap()
+    let lang = if let Some(lang) = &args.lang {
+        lang.clone()
     } else {
-        current_condition["FeelsLikeC"].as_str().unwrap()
+        Lang::EN
     };
-    let weather_code = current_condition["weatherCode"].as_str().unwrap();
 
-    let weather_icon = {
-        if args.nerd {
-            WEATHER_CODES_NERD
-        } else {
-            WEATHER_CODES
-        }
-    }
-    .iter()
-    .find(|(code, _)| *code == weather_code.parse::<i32>().unwrap())
-    .map(|(_, symbol)| symbol)
-    .unwrap();
-
-    let text = match args.custom_indicator {
-        None => {
-            let main_indicator_code = if args.fahrenheit && args.main_indicator == "temp_C" {
-                "temp_F"
-            } else {
-                args.main_indicator.as_str()
-            };
-            let indicator = current_condition[main_indicator_code].as_str().unwrap();
-            if args.vertical_view {
-                format!("{}\n{}", weather_icon, indicator)
-            } else {
-                format!("{} {}", weather_icon, indicator)
-            }
-        }
-        Some(expression) => {
-            format_indicator(current_condition, nearest_area, expression, weather_icon)
+    let layout = match Layout::from_args(&args) {
+        Ok(layout) => layout,
+        Err(err) => {
+            println!("{}", render_error(&err, &lang, "wttrbar"));
+            return;
         }
     };
-    data.insert("text", text);
 
-    let mut tooltip = format!(
-        "<b>{}</b> {}°\n",
-        lang.weather_desc_value(current_condition).unwrap(),
-        if args.fahrenheit {
-            current_condition["temp_F"].as_str().unwrap()
-        } else {
-            current_condition["temp_C"].as_str().unwrap()
-        },
-    );
-    tooltip += &format!("{}: {}°\n", lang.feels_like(), feels_like);
-    if args.mph {
-        tooltip += &format!(
-            "{}: {} mph\n",
-            lang.wind(),
-            current_condition["windspeedMiles"].as_str().unwrap()
-        );
-    } else {
-        tooltip += &format!(
-            "{}: {} km/h\n",
-            lang.wind(),
-            current_condition["windspeedKmph"].as_str().unwrap()
-        );
-    }
-    tooltip += &format!(
-        "{}: {}%\n",
-        lang.humidity(),
-        current_condition["humidity"].as_str().unwrap()
-    );
-    let nearest_area = &weather["nearest_area"][0];
-    let area_name = nearest_area["areaName"][0]["value"].as_str().unwrap();
-    let region = nearest_area["region"][0]["value"].as_str().unwrap();
-    let country = nearest_area["country"][0]["value"].as_str().unwrap();
-
-    let location_parts: Vec<&str> = vec![area_name, region, country]
+    let locations: Vec<String> = collect_locations(&args.location)
         .into_iter()
-        .filter(|part| !part.is_empty())
+        // Without a location from the time zone, the provider falls back to
+        // the IP address. `auto-geoclue` is looked up on every fetch.
+        .map(|location| {
+            if location != timezone::AUTO_TZ {
+                return location;
+            }
+            timezone::locate().unwrap_or_else(|err| {
+                eprintln!("wttrbar: {}, using IP geolocation", err);
+                String::new()
+            })
+        })
         .collect();
-
-    tooltip += &format!("{}: {}\n", lang.location(), location_parts.join(", "));
-
-    if args.observation_time {
-        if let Some(formatted_time) = get_observation_time(current_condition, args.ampm) {
-            tooltip += &format!("{}: {}\n", lang.observation_time(), formatted_time);
+    for location in &locations {
+        let checked = location::validate(location).and_then(|kind| {
+            if args.provider.supports(kind) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "{:?} only works with the wttr.in provider",
+                    location
+                ))
+            }
+        });
+        if let Err(err) = checked {
+            let err = Error::Config(format!("location: {}", err));
+            println!("{}", render_error(&err, &lang, "wttrbar"));
+            return;
         }
     }
 
-    let now = Local::now();
-
-    let today = Local::now().date_naive();
-    let mut forecast = weather["weather"].as_array().unwrap().clone();
-    forecast.retain(|item| {
-        let item_date =
-            NaiveDate::parse_from_str(item["date"].as_str().unwrap(), "%Y-%m-%d").unwrap();
-        item_date >= today
-    });
-
-    for (i, day) in forecast.iter().enumerate() {
-        tooltip += "\n<b>";
-        if i == 0 {
-            tooltip += &format!("{}, ", lang.today());
-        }
-        if i == 1 {
-            tooltip += &format!("{}, ", lang.tomorrow());
-        }
-        let date = NaiveDate::parse_from_str(day["date"].as_str().unwrap(), "%Y-%m-%d").unwrap();
-        let locale = Locale::try_from(lang.locale_str().as_str()).unwrap_or(Locale::en_US);
-        tooltip += &format!(
-            "{}</b>\n",
-            date.format_localized(args.date_format.as_str(), locale)
-        );
-
-        let (max_temp, min_temp) = if args.fahrenheit {
-            (
-                day["maxtempF"].as_str().unwrap(),
-                day["mintempF"].as_str().unwrap(),
-            )
-        } else {
-            (
-                day["maxtempC"].as_str().unwrap(),
-                day["mintempC"].as_str().unwrap(),
-            )
-        };
-
-        tooltip += &format!(
-            "{} {}° {} {}° ",
-            if args.nerd { "󰳡" } else { "⬆️" },
-            max_temp,
-            if args.nerd { "󰳛" } else { "⬇️" },
-            min_temp
-        );
-
-        let moon_phase = day["astronomy"][0]["moon_phase"].as_str().unwrap_or("");
-        let moon_illumination = day["astronomy"][0]["moon_illumination"]
-            .as_str()
-            .unwrap_or("?");
-
-        tooltip += &format!(
-            "{} {} {} {} {} {}%\n",
-            if args.nerd { "󰖜" } else { "🌅" },
-            format_ampm_time(day, "sunrise", args.ampm),
-            if args.nerd { "󰖛" } else { "🌇" },
-            format_ampm_time(day, "sunset", args.ampm),
-            format_moon_phase_icon(moon_phase, args.nerd),
-            moon_illumination
-        );
-
-        for hour in day["hourly"].as_array().unwrap() {
-            let hour_time = hour["time"].as_str().unwrap();
-            let formatted_hour_time = if hour_time.len() >= 2 {
-                hour_time[..hour_time.len() - 2].to_string()
+    let sources: Vec<Source> = locations
+        .into_iter()
+        .map(|location| {
+            let provider = args.provider.build(args.base_url.clone(), lang.clone());
+            let cache = if args.no_cache {
+                None
             } else {
-                hour_time.to_string()
+                Some(Cache::new(
+                    args.cache_dir.clone().unwrap_or_else(cache::default_dir),
+                    Duration::from_secs(args.cache_ttl),
+                    Duration::from_secs(args.cache_max_age),
+                ))
             };
-            if i == 0
-                && now.hour() >= 2
-                && formatted_hour_time.parse::<u32>().unwrap() < now.hour() - 2
-            {
-                continue;
-            }
+            Source::new(provider, location, cache, args.geoclue_precision)
+        })
+        .collect();
+    let http = Http::new(&args);
 
-            let sep = if args.tabs { "\t" } else { " " };
-            let mut tooltip_line = format!(
-                "{}{sep}{}{sep}{}{sep}{}",
-                format_time(hour["time"].as_str().unwrap(), args.ampm),
-                if args.nerd {
-                    WEATHER_CODES_NERD
-                } else {
-                    WEATHER_CODES
-                }
-                .iter()
-                .find(|(code, _)| *code
-                    == hour["weatherCode"]
-                        .as_str()
-                        .unwrap()
-                        .parse::<i32>()
-                        .unwrap())
-                .map(|(_, symbol)| symbol)
-                .unwrap(),
-                if args.fahrenheit {
-                    format_temp(hour["FeelsLikeF"].as_str().unwrap())
-                } else {
-                    format_temp(hour["FeelsLikeC"].as_str().unwrap())
-                },
-                lang.weather_desc_value(hour).unwrap(),
-            );
-            if !args.hide_conditions {
-                tooltip_line += format!(", {}", format_chances(hour, &lang)).as_str();
-            }
-            tooltip_line += "\n";
-            tooltip += &tooltip_line;
-        }
+    if args.daemon {
+        daemon::run(&args, &lang, &sources, &http, &layout);
     }
-    data.insert("tooltip", tooltip);
 
-    let css_class = lang
-        .weather_desc_value(current_condition)
-        .unwrap_or("")
-        .to_lowercase()
-        .split(',')
-        .next()
-        .map(|s| s.trim().replace(' ', "_"))
-        .unwrap_or_default();
-    data.insert("class", css_class);
-
-    let json_data = json!(data);
-    println!("{}", json_data);
+    let max_age = Duration::from_secs(args.cache_max_age);
+    let mut states: Vec<_> = sources.iter().map(Source::state).collect();
+    for (state, result) in states.iter_mut().zip(load_all(&sources, &http, false)) {
+        state.update(result, max_age);
+    }
+    if let Some(output) = render(&states, &args, &lang, &layout, &layout.indicators[0]) {
+        println!("{}", output);
+    }
 }
@@ -0,0 +1,366 @@
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+
+/// A forecast in the layout of wttr.in's `?format=j1` output. Every field is
+/// optional and tolerates an unexpected type, so a response that lacks some
+/// keys or has a few odd values still renders everything else.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Weather {
+    #[serde(deserialize_with = "lenient")]
+    pub current_condition: Vec<CurrentCondition>,
+    #[serde(deserialize_with = "lenient")]
+    pub nearest_area: Vec<Area>,
+    #[serde(deserialize_with = "lenient")]
+    pub weather: Vec<Day>,
+}
+
+/// wttr.in wraps texts like descriptions and area names in `[{"value": ...}]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Text {
+    #[serde(deserialize_with = "lenient")]
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CurrentCondition {
+    #[serde(rename = "temp_C", deserialize_with = "lenient_text")]
+    pub temp_c: Option<String>,
+    #[serde(rename = "temp_F", deserialize_with = "lenient_text")]
+    pub temp_f: Option<String>,
+    #[serde(rename = "FeelsLikeC", deserialize_with = "lenient_text")]
+    pub feels_like_c: Option<String>,
+    #[serde(rename = "FeelsLikeF", deserialize_with = "lenient_text")]
+    pub feels_like_f: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub humidity: Option<String>,
+    #[serde(rename = "weatherCode", deserialize_with = "lenient_text")]
+    pub weather_code: Option<String>,
+    #[serde(rename = "weatherDesc", deserialize_with = "lenient")]
+    pub weather_desc: Vec<Text>,
+    #[serde(rename = "windspeedKmph", deserialize_with = "lenient_text")]
+    pub windspeed_kmph: Option<String>,
+    #[serde(rename = "windspeedMiles", deserialize_with = "lenient_text")]
+    pub windspeed_miles: Option<String>,
+    #[serde(rename = "winddirDegree", deserialize_with = "lenient_text")]
+    pub winddir_degree: Option<String>,
+    #[serde(rename = "winddir16Point", deserialize_with = "lenient_text")]
+    pub winddir_16_point: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub pressure: Option<String>,
+    #[serde(rename = "pressureInches", deserialize_with = "lenient_text")]
+    pub pressure_inches: Option<String>,
+    #[serde(rename = "precipMM", deserialize_with = "lenient_text")]
+    pub precip_mm: Option<String>,
+    #[serde(rename = "precipInches", deserialize_with = "lenient_text")]
+    pub precip_inches: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub visibility: Option<String>,
+    #[serde(rename = "visibilityMiles", deserialize_with = "lenient_text")]
+    pub visibility_miles: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub cloudcover: Option<String>,
+    #[serde(rename = "uvIndex", deserialize_with = "lenient_text")]
+    pub uv_index: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub observation_time: Option<String>,
+    #[serde(rename = "localObsDateTime", deserialize_with = "lenient_text")]
+    pub local_obs_date_time: Option<String>,
+    /// Keys without a field of their own, like the localized `lang_xx`
+    /// descriptions.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Area {
+    #[serde(rename = "areaName", deserialize_with = "lenient")]
+    pub area_name: Vec<Text>,
+    #[serde(deserialize_with = "lenient")]
+    pub region: Vec<Text>,
+    #[serde(deserialize_with = "lenient")]
+    pub country: Vec<Text>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub latitude: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub longitude: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub population: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Day {
+    #[serde(deserialize_with = "lenient_text")]
+    pub date: Option<String>,
+    #[serde(rename = "maxtempC", deserialize_with = "lenient_text")]
+    pub maxtemp_c: Option<String>,
+    #[serde(rename = "maxtempF", deserialize_with = "lenient_text")]
+    pub maxtemp_f: Option<String>,
+    #[serde(rename = "mintempC", deserialize_with = "lenient_text")]
+    pub mintemp_c: Option<String>,
+    #[serde(rename = "mintempF", deserialize_with = "lenient_text")]
+    pub mintemp_f: Option<String>,
+    #[serde(rename = "avgtempC", deserialize_with = "lenient_text")]
+    pub avgtemp_c: Option<String>,
+    #[serde(rename = "avgtempF", deserialize_with = "lenient_text")]
+    pub avgtemp_f: Option<String>,
+    #[serde(rename = "sunHour", deserialize_with = "lenient_text")]
+    pub sun_hour: Option<String>,
+    #[serde(rename = "totalSnow_cm", deserialize_with = "lenient_text")]
+    pub total_snow_cm: Option<String>,
+    #[serde(rename = "uvIndex", deserialize_with = "lenient_text")]
+    pub uv_index: Option<String>,
+    #[serde(deserialize_with = "lenient")]
+    pub astronomy: Vec<Astronomy>,
+    #[serde(deserialize_with = "lenient")]
+    pub hourly: Vec<Hourly>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Astronomy {
+    #[serde(deserialize_with = "lenient_text")]
+    pub sunrise: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub sunset: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub moonrise: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub moonset: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub moon_phase: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub moon_illumination: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hourly {
+    #[serde(deserialize_with = "lenient_text")]
+    pub time: Option<String>,
+    #[serde(rename = "tempC", deserialize_with = "lenient_text")]
+    pub temp_c: Option<String>,
+    #[serde(rename = "tempF", deserialize_with = "lenient_text")]
+    pub temp_f: Option<String>,
+    #[serde(rename = "FeelsLikeC", deserialize_with = "lenient_text")]
+    pub feels_like_c: Option<String>,
+    #[serde(rename = "FeelsLikeF", deserialize_with = "lenient_text")]
+    pub feels_like_f: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub humidity: Option<String>,
+    #[serde(rename = "weatherCode", deserialize_with = "lenient_text")]
+    pub weather_code: Option<String>,
+    #[serde(rename = "weatherDesc", deserialize_with = "lenient")]
+    pub weather_desc: Vec<Text>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceoffog: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceoffrost: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceofovercast: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceofrain: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceofsnow: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceofsunshine: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceofthunder: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub chanceofwindy: Option<String>,
+    #[serde(rename = "windspeedKmph", deserialize_with = "lenient_text")]
+    pub windspeed_kmph: Option<String>,
+    #[serde(rename = "windspeedMiles", deserialize_with = "lenient_text")]
+    pub windspeed_miles: Option<String>,
+    #[serde(rename = "winddirDegree", deserialize_with = "lenient_text")]
+    pub winddir_degree: Option<String>,
+    #[serde(rename = "winddir16Point", deserialize_with = "lenient_text")]
+    pub winddir_16_point: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub pressure: Option<String>,
+    #[serde(rename = "pressureInches", deserialize_with = "lenient_text")]
+    pub pressure_inches: Option<String>,
+    #[serde(rename = "precipMM", deserialize_with = "lenient_text")]
+    pub precip_mm: Option<String>,
+    #[serde(rename = "precipInches", deserialize_with = "lenient_text")]
+    pub precip_inches: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub visibility: Option<String>,
+    #[serde(rename = "visibilityMiles", deserialize_with = "lenient_text")]
+    pub visibility_miles: Option<String>,
+    #[serde(deserialize_with = "lenient_text")]
+    pub cloudcover: Option<String>,
+    #[serde(rename = "uvIndex", deserialize_with = "lenient_text")]
+    pub uv_index: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl Weather {
+    pub fn current_condition(&self) -> CurrentCondition {
+        self.current_condition.first().cloned().unwrap_or_default()
+    }
+
+    pub fn nearest_area(&self) -> Area {
+        self.nearest_area.first().cloned().unwrap_or_default()
+    }
+}
+
+impl Day {
+    pub fn astronomy(&self) -> Astronomy {
+        self.astronomy.first().cloned().unwrap_or_default()
+    }
+}
+
+/// Access to a j1 object by its original key, for the user facing options
+/// (`--main-indicator`, `--custom-indicator`) that accept any key.
+pub trait Keyed: Serialize {
+    fn to_json(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+impl Keyed for CurrentCondition {}
+impl Keyed for Area {}
+impl Keyed for Day {}
+impl Keyed for Hourly {}
+
+/// The weather description of a `current_condition` or `hourly` entry under
+/// its j1 key, `weatherDesc` or one of the localized `lang_xx`.
+pub trait Described {
+    fn description(&self, key: &str) -> Option<&str>;
+}
+
+impl Described for CurrentCondition {
+    fn description(&self, key: &str) -> Option<&str> {
+        description(&self.weather_desc, &self.extra, key)
+    }
+}
+
+impl Described for Hourly {
+    fn description(&self, key: &str) -> Option<&str> {
+        description(&self.weather_desc, &self.extra, key)
+    }
+}
+
+fn description<'a>(
+    weather_desc: &'a [Text],
+    extra: &'a Map<String, Value>,
+    key: &str,
+) -> Option<&'a str> {
+    match key {
+        "weatherDesc" => first_text(weather_desc),
+        key => extra.get(key)?[0]["value"].as_str(),
+    }
+}
+
+pub fn text_value(value: &Value) -> Option<&str> {
+    match value {
+        Value::Array(list) => list.first()?["value"].as_str(),
+        value => value.as_str(),
+    }
+}
+
+pub fn first_text(texts: &[Text]) -> Option<&str> {
+    texts.first().map(|text| text.value.as_str())
+}
+
+/// Deserialize a field, falling back to its default when it has an unexpected
+/// shape instead of failing the whole response.
+fn lenient<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + Default,
+{
+    let value = Value::deserialize(deserializer)?;
+    Ok(T::deserialize(value).unwrap_or_default())
+}
+
+/// Deserialize a text value. wttr.in sends numbers as strings, but a number
+/// is taken as well; any other shape counts as missing.
+fn lenient_text<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(text) => Some(text),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_fields_next_to_missing_or_odd_ones() {
+        let weather: Weather = serde_json::from_value(json!({
+            "current_condition": [{
+                "temp_C": 7,
+                "FeelsLikeC": {"value": "5"},
+                "humidity": null,
+                "weatherDesc": [{"value": 61}],
+                "winddir16Point": "SW"
+            }],
+            "weather": [{
+                "date": "2026-10-18",
+                "maxtempC": [9],
+                "hourly": [{"time": 0, "chanceofrain": "40"}]
+            }]
+        }))
+        .unwrap();
+
+        let current = weather.current_condition();
+        assert_eq!(current.temp_c.as_deref(), Some("7"));
+        assert_eq!(current.feels_like_c, None);
+        assert_eq!(current.humidity, None);
+        assert_eq!(current.pressure, None);
+        assert_eq!(current.weather_desc[0].value, "");
+        assert_eq!(current.winddir_16_point.as_deref(), Some("SW"));
+
+        let day = &weather.weather[0];
+        assert_eq!(day.maxtemp_c, None);
+        assert_eq!(day.hourly[0].time.as_deref(), Some("0"));
+        assert_eq!(day.hourly[0].chanceofrain.as_deref(), Some("40"));
+        // Without nearest_area and astronomy, there are empty defaults.
+        assert!(first_text(&weather.nearest_area().area_name).is_none());
+        assert_eq!(day.astronomy().sunrise, None);
+    }
+
+    #[test]
+    fn reads_localized_descriptions() {
+        let hour: Hourly = serde_json::from_value(json!({
+            "weatherDesc": [{"value": "Light rain"}],
+            "lang_de": [{"value": "Leichter Regen"}]
+        }))
+        .unwrap();
+        assert_eq!(hour.description("lang_de"), Some("Leichter Regen"));
+        assert_eq!(hour.description("weatherDesc"), Some("Light rain"));
+        assert_eq!(hour.description("lang_fr"), None);
+    }
+
+    #[test]
+    fn ignores_lists_of_the_wrong_shape() {
+        let weather: Weather = serde_json::from_value(json!({
+            "current_condition": {"temp_C": "7"},
+            "nearest_area": "Amsterdam",
+            "weather": [{"astronomy": [1, 2], "hourly": "none"}]
+        }))
+        .unwrap();
+        assert!(weather.current_condition.is_empty());
+        assert!(weather.nearest_area.is_empty());
+        assert!(weather.weather[0].astronomy.is_empty());
+        assert!(weather.weather[0].hourly.is_empty());
+    }
+}
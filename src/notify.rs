@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+use std::fs::{self, read_to_string};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde_json::Value;
+use zbus::blocking::Connection;
+use zbus::zvariant;
+
+use crate::cli::Args;
+use crate::format::{escape_markup, location_now};
+use crate::lang::Lang;
+use crate::model::{text_value, Keyed};
+use crate::render::{forecast_placeholders, location_parts, matching_alerts, unit_values, Layout};
+use crate::source::State;
+use crate::units::Units;
+
+/// How long an event has to be over before it's notified again.
+const COOLDOWN: Duration = Duration::from_secs(3 * 60 * 60);
+
+const STATE_FILE: &str = "notifications.json";
+
+/// Something worth a desktop notification. `key` identifies the event for
+/// de-duplication, like `Amsterdam/rain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub key: String,
+    pub summary: String,
+    pub body: String,
+    pub icon: &'static str,
+}
+
+/// Sends `--notify` notifications over D-Bus. Which events were already
+/// notified is kept in a file next to the cache, so restarting the daemon
+/// doesn't repeat them.
+pub struct Notifier {
+    path: PathBuf,
+    sent: HashMap<String, u64>,
+    connection: Option<Connection>,
+}
+
+impl Notifier {
+    pub fn new(dir: PathBuf) -> Self {
+        let path = dir.join(STATE_FILE);
+        let sent = read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        Notifier {
+            path,
+            sent,
+            connection: None,
+        }
+    }
+
+    /// Send the notifications that weren't sent recently. Failures are only
+    /// reported on stderr, the bar keeps working without notifications.
+    pub fn send(&mut self, notifications: &[Notification]) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let fresh = update_sent(&mut self.sent, notifications, now);
+        for notification in fresh {
+            if let Err(err) = self.notify(notification) {
+                eprintln!("wttrbar: cannot send notification: {}", err);
+                self.connection = None;
+            }
+        }
+        let saved = fs::create_dir_all(self.path.parent().unwrap_or(&self.path)).and_then(|_| {
+            fs::write(
+                &self.path,
+                serde_json::to_string(&self.sent).unwrap_or_default(),
+            )
+        });
+        if let Err(err) = saved {
+            eprintln!("wttrbar: cannot write {}: {}", self.path.display(), err);
+        }
+    }
+
+    fn notify(&mut self, notification: &Notification) -> zbus::Result<()> {
+        let connection = match &self.connection {
+            Some(connection) => connection,
+            None => self.connection.insert(Connection::session()?),
+        };
+        let hints: HashMap<&str, zvariant::Value> = HashMap::new();
+        connection.call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                "wttrbar",
+                0u32,
+                notification.icon,
+                notification.summary.as_str(),
+                notification.body.as_str(),
+                Vec::<&str>::new(),
+                hints,
+                -1i32,
+            ),
+        )?;
+        Ok(())
+    }
+}
+
+/// Record the notifications that are due and return them. An event is
+/// remembered until it has been over for the [`COOLDOWN`], so it isn't
+/// repeated while it lasts or when it flickers on and off.
+fn update_sent<'a>(
+    sent: &mut HashMap<String, u64>,
+    notifications: &'a [Notification],
+    now: u64,
+) -> Vec<&'a Notification> {
+    let mut fresh = vec![];
+    for notification in notifications {
+        if sent.insert(notification.key.clone(), now).is_none() {
+            fresh.push(notification);
+        }
+    }
+    sent.retain(|_, seen| now.saturating_sub(*seen) < COOLDOWN.as_secs());
+    fresh
+}
+
+/// The events in the current forecasts: rain or snow starting within the
+/// next hour, and `--alert` rules that match.
+pub fn notifications(
+    states: &[State],
+    args: &Args,
+    lang: &Lang,
+    layout: &Layout,
+) -> Vec<Notification> {
+    let units = Units::from_args(args);
+    let mut notifications = vec![];
+    for state in states {
+        let Some(forecast) = &state.forecast else {
+            continue;
+        };
+        let weather = &forecast.weather;
+        let current_values = unit_values(weather.current_condition().to_json(), &units);
+        let now = location_now(&weather.current_condition());
+        let placeholders = forecast_placeholders(weather, now);
+        let nearest_area = weather.nearest_area();
+        let name = location_parts(&nearest_area)
+            .first()
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| state.location.clone());
+
+        let number = |value: &Value, key: &str| {
+            text_value(&value[key]).and_then(|value| value.trim().parse::<f64>().ok())
+        };
+        let dry = number(&current_values, "precipMM").is_some_and(|precip| precip == 0.0);
+        let precipitation = [
+            (
+                "rain",
+                "chanceofrain",
+                lang.rain_soon(),
+                lang.rain(),
+                "weather-showers",
+            ),
+            (
+                "snow",
+                "chanceofsnow",
+                lang.snow_soon(),
+                lang.snow(),
+                "weather-snow",
+            ),
+        ];
+        for (event, key, summary, label, icon) in precipitation {
+            let Some(chance) = number(&placeholders["next_hour"], key) else {
+                continue;
+            };
+            if dry && chance >= f64::from(args.notify_chance) {
+                notifications.push(Notification {
+                    key: format!("{}/{}", name, event),
+                    summary,
+                    body: format!("{}: {} {}%", escape_markup(&name), label, chance),
+                    icon,
+                });
+            }
+        }
+
+        for alert in matching_alerts(&current_values, &placeholders, layout) {
+            notifications.push(Notification {
+                key: format!("{}/{}", name, alert.class()),
+                summary: format!("{}: {}", lang.alerts(), alert.name),
+                // Notification servers with body-markup parse it like Pango.
+                body: format!("{}: {}", escape_markup(&name), escape_markup(&alert.rule)),
+                icon: "dialog-warning",
+            });
+        }
+    }
+    notifications
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::Forecast;
+    use chrono::{Days, Local};
+    use clap::Parser;
+    use serde_json::json;
+    use std::time::SystemTime;
+
+    /// Amsterdam with `precip` mm now and every hourly row of today and
+    /// tomorrow at `chance`% rain.
+    fn state(precip: &str, chance: &str) -> State {
+        let today = Local::now().date_naive();
+        let day = |date: chrono::NaiveDate| {
+            let hourly: Vec<Value> = (0..8)
+                .map(|i| json!({"time": (i * 300).to_string(), "chanceofrain": chance}))
+                .collect();
+            json!({"date": date.format("%Y-%m-%d").to_string(), "hourly": hourly})
+        };
+        let weather = serde_json::from_value(json!({
+            "current_condition": [{"precipMM": precip}],
+            "nearest_area": [{"areaName": [{"value": "Amsterdam"}]}],
+            "weather": [day(today), day(today + Days::new(1))]
+        }))
+        .unwrap();
+        State {
+            location: "Amsterdam".to_string(),
+            provider: "wttr.in",
+            forecast: Some(Forecast {
+                weather,
+                fetched_at: SystemTime::now(),
+                stale: false,
+                warning: None,
+            }),
+            error: None,
+            loaded_at: None,
+            failures: 0,
+        }
+    }
+
+    fn keys(states: &[State], cli: &[&str]) -> Vec<String> {
+        let args = Args::parse_from([&["wttrbar"], cli].concat());
+        let layout = Layout::from_args(&args).unwrap();
+        notifications(states, &args, &Lang::EN, &layout)
+            .into_iter()
+            .map(|notification| notification.key)
+            .collect()
+    }
+
+    #[test]
+    fn notifies_rain_starting_soon() {
+        assert_eq!(keys(&[state("0.0", "80")], &[]), ["Amsterdam/rain"]);
+        // Already raining.
+        assert!(keys(&[state("0.4", "80")], &[]).is_empty());
+        assert!(keys(&[state("0.0", "30")], &[]).is_empty());
+        assert_eq!(
+            keys(&[state("0.0", "30")], &["--notify-chance", "30"]),
+            ["Amsterdam/rain"]
+        );
+    }
+
+    #[test]
+    fn escapes_alert_rules() {
+        let args = Args::parse_from(["wttrbar", "--alert", "dry: precipMM < 1"]);
+        let layout = Layout::from_args(&args).unwrap();
+        let notifications = notifications(&[state("0.0", "0")], &args, &Lang::EN, &layout);
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].key, "Amsterdam/alert-dry");
+        assert_eq!(notifications[0].body, "Amsterdam: precipMM &lt; 1");
+    }
+
+    fn notification(key: &str) -> Notification {
+        Notification {
+            key: key.to_string(),
+            summary: String::new(),
+            body: String::new(),
+            icon: "",
+        }
+    }
+
+    #[test]
+    fn sends_each_event_once_per_cooldown() {
+        let mut sent = HashMap::new();
+        let rain = [notification("Amsterdam/rain")];
+        let cooldown = COOLDOWN.as_secs();
+
+        assert_eq!(update_sent(&mut sent, &rain, 1000).len(), 1);
+        assert!(update_sent(&mut sent, &rain, 1060).is_empty());
+        // Still raining long after: no repeat.
+        assert!(update_sent(&mut sent, &rain, 1000 + 2 * cooldown).is_empty());
+
+        // The rain stopped, the entry stays until the cooldown is over.
+        assert!(update_sent(&mut sent, &[], 1000 + 2 * cooldown + 60).is_empty());
+        assert!(sent.contains_key("Amsterdam/rain"));
+        update_sent(&mut sent, &[], 1000 + 3 * cooldown);
+        assert!(sent.is_empty());
+        assert_eq!(update_sent(&mut sent, &rain, 1000 + 3 * cooldown).len(), 1);
+    }
+}
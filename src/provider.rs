@@ -0,0 +1,52 @@
+use clap::ValueEnum;
+
+use crate::error::Error;
+use crate::http::Http;
+use crate::lang::Lang;
+use crate::location::LocationKind;
+use crate::model::Weather;
+
+mod open_meteo;
+mod wttr_in;
+
+pub use open_meteo::OpenMeteo;
+pub use wttr_in::WttrIn;
+
+/// A source of weather data. Every provider normalizes its response into
+/// [`Weather`], which follows the layout of wttr.in's `?format=j1` output
+/// (`current_condition`, `nearest_area` and `weather` with `hourly` and
+/// `astronomy`).
+pub trait Provider: Send + Sync {
+    /// Human readable name, used in error tooltips.
+    fn name(&self) -> &'static str;
+
+    /// Distinguishes cache files of different providers, instances and
+    /// languages for the same location.
+    fn cache_key(&self) -> String;
+
+    fn fetch(&self, http: &Http, location: &str) -> Result<Weather, Error>;
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ProviderKind {
+    WttrIn,
+    OpenMeteo,
+}
+
+impl ProviderKind {
+    pub fn build(&self, base_url: Option<String>, lang: Lang) -> Box<dyn Provider> {
+        match self {
+            Self::WttrIn => Box::new(WttrIn::new(base_url, lang)),
+            Self::OpenMeteo => Box::new(OpenMeteo::new(lang)),
+        }
+    }
+
+    /// Whether the provider understands locations of this form. Open-Meteo
+    /// only geocodes names and takes coordinates.
+    pub fn supports(&self, kind: LocationKind) -> bool {
+        match self {
+            Self::WttrIn => true,
+            Self::OpenMeteo => !matches!(kind, LocationKind::Landmark | LocationKind::Domain),
+        }
+    }
+}
@@ -0,0 +1,459 @@
+use chrono::{Duration, NaiveDateTime, Timelike};
+use reqwest::Url;
+use serde_json::{json, Map, Value};
+
+use super::Provider;
+use crate::constants::WMO_CODES;
+use crate::error::Error;
+use crate::format::format_rounded;
+use crate::http::Http;
+use crate::lang::Lang;
+use crate::model::Weather;
+
+const GEOCODING_URL: &str = "https://geocoding-api.open-meteo.com/v1/search";
+const FORECAST_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+// Open-Meteo provides every hourly variable as a current condition as well.
+const CONDITION_FIELDS: &str = "temperature_2m,relative_humidity_2m,apparent_temperature,\
+precipitation,weather_code,cloud_cover,pressure_msl,wind_speed_10m,wind_direction_10m,\
+visibility,uv_index";
+// The chance of rain only exists per hour.
+const HOURLY_FIELDS: &str = "temperature_2m,relative_humidity_2m,apparent_temperature,\
+precipitation,precipitation_probability,weather_code,cloud_cover,pressure_msl,wind_speed_10m,\
+wind_direction_10m,visibility,uv_index";
+const DAILY_FIELDS: &str = "temperature_2m_max,temperature_2m_min,sunrise,sunset";
+const FORECAST_DAYS: &str = "3";
+
+pub struct OpenMeteo {
+    lang: Lang,
+}
+
+struct Place {
+    name: String,
+    region: String,
+    country: String,
+    latitude: f64,
+    longitude: f64,
+}
+
+impl OpenMeteo {
+    pub fn new(lang: Lang) -> Self {
+        OpenMeteo { lang }
+    }
+
+    /// Open-Meteo only knows coordinates, so names are resolved with its
+    /// geocoding API first. `lat,lon` locations are used as they are.
+    fn resolve(&self, http: &Http, location: &str) -> Result<Place, Error> {
+        if location.is_empty() {
+            return Err(Error::MissingLocation);
+        }
+        if let Some((latitude, longitude)) = parse_coordinates(location) {
+            return Ok(Place {
+                name: location.to_string(),
+                region: String::new(),
+                country: String::new(),
+                latitude,
+                longitude,
+            });
+        }
+
+        let url = Url::parse_with_params(
+            GEOCODING_URL,
+            &[
+                ("name", location),
+                ("count", "1"),
+                ("language", self.lang.code().as_str()),
+                ("format", "json"),
+            ],
+        )
+        .map_err(|_| Error::UnknownLocation(location.to_string()))?;
+        let response: Value = http.get_json(url.as_str())?;
+        let result = &response["results"][0];
+        let unknown = || Error::UnknownLocation(location.to_string());
+
+        Ok(Place {
+            name: result["name"].as_str().ok_or_else(unknown)?.to_string(),
+            region: result["admin1"].as_str().unwrap_or("").to_string(),
+            country: result["country"].as_str().unwrap_or("").to_string(),
+            latitude: result["latitude"].as_f64().ok_or_else(unknown)?,
+            longitude: result["longitude"].as_f64().ok_or_else(unknown)?,
+        })
+    }
+}
+
+impl Provider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "Open-Meteo"
+    }
+
+    fn cache_key(&self) -> String {
+        format!("open-meteo-{}", self.lang.code())
+    }
+
+    fn fetch(&self, http: &Http, location: &str) -> Result<Weather, Error> {
+        let place = self.resolve(http, location)?;
+        let url = forecast_url(&place).ok_or(Error::Parse)?;
+        let forecast: Value = http.get_json(url.as_str())?;
+        to_j1(&place, &forecast)
+            .and_then(|weather| serde_json::from_value(weather).ok())
+            .ok_or(Error::Parse)
+    }
+}
+
+fn forecast_url(place: &Place) -> Option<Url> {
+    Url::parse_with_params(
+        FORECAST_URL,
+        &[
+            ("latitude", place.latitude.to_string().as_str()),
+            ("longitude", place.longitude.to_string().as_str()),
+            ("current", CONDITION_FIELDS),
+            ("hourly", HOURLY_FIELDS),
+            ("daily", DAILY_FIELDS),
+            ("timezone", "auto"),
+            ("forecast_days", FORECAST_DAYS),
+        ],
+    )
+    .ok()
+}
+
+fn parse_coordinates(location: &str) -> Option<(f64, f64)> {
+    let (latitude, longitude) = location.split_once(',')?;
+    Some((
+        latitude.trim().parse().ok()?,
+        longitude.trim().parse().ok()?,
+    ))
+}
+
+/// Convert an Open-Meteo forecast into wttr.in's j1 layout.
+fn to_j1(place: &Place, forecast: &Value) -> Option<Value> {
+    let current = &forecast["current"];
+    let local_time = parse_iso_time(current["time"].as_str()?)?;
+    let utc_offset = forecast["utc_offset_seconds"].as_i64().unwrap_or(0);
+    let utc_time = local_time - Duration::seconds(utc_offset);
+
+    let mut current_condition = conditions(|key| current[key].as_f64());
+    if let Some(temp) = current["temperature_2m"].as_f64() {
+        current_condition.insert("temp_C".to_string(), json!(format_rounded(temp, 0)));
+        current_condition.insert(
+            "temp_F".to_string(),
+            json!(format_rounded(to_fahrenheit(temp), 0)),
+        );
+    }
+    current_condition.insert(
+        "observation_time".to_string(),
+        json!(utc_time.format("%I:%M %p").to_string()),
+    );
+    current_condition.insert(
+        "localObsDateTime".to_string(),
+        json!(local_time.format("%Y-%m-%d %I:%M %p").to_string()),
+    );
+
+    let hourly = &forecast["hourly"];
+    let hourly_times = hourly["time"].as_array()?;
+    let daily = &forecast["daily"];
+    let mut days = vec![];
+    for (i, date) in daily["time"].as_array()?.iter().enumerate() {
+        let date = date.as_str()?;
+        let mut day = Map::new();
+        day.insert("date".to_string(), json!(date));
+        if let Some(max) = daily["temperature_2m_max"][i].as_f64() {
+            day.insert("maxtempC".to_string(), json!(format_rounded(max, 0)));
+            day.insert(
+                "maxtempF".to_string(),
+                json!(format_rounded(to_fahrenheit(max), 0)),
+            );
+        }
+        if let Some(min) = daily["temperature_2m_min"][i].as_f64() {
+            day.insert("mintempC".to_string(), json!(format_rounded(min, 0)));
+            day.insert(
+                "mintempF".to_string(),
+                json!(format_rounded(to_fahrenheit(min), 0)),
+            );
+        }
+        let mut astronomy = Map::new();
+        for key in ["sunrise", "sunset"] {
+            if let Some(time) = daily[key][i].as_str().and_then(parse_iso_time) {
+                astronomy.insert(key.to_string(), json!(time.format("%I:%M %p").to_string()));
+            }
+        }
+        day.insert("astronomy".to_string(), json!([astronomy]));
+
+        // wttr.in reports every third hour, so do the same here.
+        let mut hours = vec![];
+        for (j, time) in hourly_times.iter().enumerate() {
+            let time = match time.as_str().and_then(parse_iso_time) {
+                Some(time) => time,
+                None => continue,
+            };
+            if time.date().format("%Y-%m-%d").to_string() != date || time.hour() % 3 != 0 {
+                continue;
+            }
+            let mut hour = conditions(|key| hourly[key][j].as_f64());
+            hour.insert("time".to_string(), json!((time.hour() * 100).to_string()));
+            if let Some(temp) = hourly["temperature_2m"][j].as_f64() {
+                hour.insert("tempC".to_string(), json!(format_rounded(temp, 0)));
+                hour.insert(
+                    "tempF".to_string(),
+                    json!(format_rounded(to_fahrenheit(temp), 0)),
+                );
+            }
+            if let Some(chance) = hourly["precipitation_probability"][j].as_f64() {
+                let code = hourly["weather_code"][j].as_i64().unwrap_or(0);
+                let key = match code {
+                    71..=77 | 85 | 86 => "chanceofsnow",
+                    95..=99 => "chanceofthunder",
+                    _ => "chanceofrain",
+                };
+                hour.insert(key.to_string(), json!(format_rounded(chance, 0)));
+            }
+            hours.push(Value::Object(hour));
+        }
+        day.insert("hourly".to_string(), json!(hours));
+        days.push(Value::Object(day));
+    }
+
+    Some(json!({
+        "current_condition": [current_condition],
+        "nearest_area": [{
+            "areaName": [{"value": place.name}],
+            "region": [{"value": place.region}],
+            "country": [{"value": place.country}],
+            "latitude": place.latitude.to_string(),
+            "longitude": place.longitude.to_string(),
+        }],
+        "weather": days,
+    }))
+}
+
+/// The keys `current_condition` and `hourly` entries have in common.
+fn conditions(get: impl Fn(&str) -> Option<f64>) -> Map<String, Value> {
+    let mut map = Map::new();
+    let mut insert = |key: &str, value: Value| {
+        map.insert(key.to_string(), value);
+    };
+
+    if let Some(code) = get("weather_code") {
+        let (wttr_code, description) = WMO_CODES
+            .iter()
+            .find(|(wmo, _, _)| *wmo == code as i32)
+            .map(|(_, wttr_code, description)| (*wttr_code, *description))
+            .unwrap_or((119, "Unknown"));
+        insert("weatherCode", json!(wttr_code.to_string()));
+        insert("weatherDesc", json!([{ "value": description }]));
+    }
+    if let Some(feels_like) = get("apparent_temperature") {
+        insert("FeelsLikeC", json!(format_rounded(feels_like, 0)));
+        insert(
+            "FeelsLikeF",
+            json!(format_rounded(to_fahrenheit(feels_like), 0)),
+        );
+    }
+    if let Some(humidity) = get("relative_humidity_2m") {
+        insert("humidity", json!(format_rounded(humidity, 0)));
+    }
+    if let Some(speed) = get("wind_speed_10m") {
+        insert("windspeedKmph", json!(format_rounded(speed, 0)));
+        insert("windspeedMiles", json!(format_rounded(speed * 0.621371, 0)));
+    }
+    if let Some(degree) = get("wind_direction_10m") {
+        insert("winddirDegree", json!(format_rounded(degree, 0)));
+        insert("winddir16Point", json!(compass_point(degree).to_string()));
+    }
+    if let Some(pressure) = get("pressure_msl") {
+        insert("pressure", json!(format_rounded(pressure, 0)));
+        insert(
+            "pressureInches",
+            json!(format_rounded(pressure * 0.02953, 2)),
+        );
+    }
+    if let Some(precipitation) = get("precipitation") {
+        insert("precipMM", json!(format!("{:.1}", precipitation)));
+        insert(
+            "precipInches",
+            json!(format!("{:.1}", precipitation / 25.4)),
+        );
+    }
+    if let Some(cloud_cover) = get("cloud_cover") {
+        insert("cloudcover", json!(format_rounded(cloud_cover, 0)));
+    }
+    if let Some(visibility) = get("visibility") {
+        insert("visibility", json!(format_rounded(visibility / 1000.0, 0)));
+        insert(
+            "visibilityMiles",
+            json!(format_rounded(visibility / 1609.344, 0)),
+        );
+    }
+    if let Some(uv_index) = get("uv_index") {
+        insert("uvIndex", json!(format_rounded(uv_index, 0)));
+    }
+    map
+}
+
+fn parse_iso_time(time: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok()
+}
+
+fn to_fahrenheit(celsius: f64) -> f64 {
+    celsius * 9.0 / 5.0 + 32.0
+}
+
+fn compass_point(degree: f64) -> &'static str {
+    const POINTS: [&str; 16] = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+    POINTS[((degree.rem_euclid(360.0) / 22.5).round() as usize) % 16]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_forecast() -> Value {
+        let hours: Vec<String> = (0..24)
+            .map(|hour| format!("2024-01-15T{:02}:00", hour))
+            .collect();
+        json!({
+            "utc_offset_seconds": 3600,
+            "current": {
+                "time": "2024-01-15T20:15",
+                "temperature_2m": 7.4,
+                "apparent_temperature": -0.3,
+                "relative_humidity_2m": 81,
+                "weather_code": 61,
+                "wind_speed_10m": 19.2,
+                "wind_direction_10m": 225,
+                "pressure_msl": 1013.2
+            },
+            "hourly": {
+                "time": hours,
+                "temperature_2m": vec![5.0; 24],
+                "weather_code": vec![71; 24],
+                "precipitation_probability": vec![40; 24]
+            },
+            "daily": {
+                "time": ["2024-01-15"],
+                "temperature_2m_max": [8.6],
+                "temperature_2m_min": [2.1],
+                "sunrise": ["2024-01-15T08:41"],
+                "sunset": ["2024-01-15T17:02"]
+            }
+        })
+    }
+
+    fn make_place() -> Place {
+        Place {
+            name: "Amsterdam".to_string(),
+            region: "North Holland".to_string(),
+            country: "Netherlands".to_string(),
+            latitude: 52.37,
+            longitude: 4.89,
+        }
+    }
+
+    #[test]
+    fn converts_current_condition_to_j1() {
+        let weather = to_j1(&make_place(), &make_forecast()).unwrap();
+        let current = &weather["current_condition"][0];
+        assert_eq!(current["temp_C"], "7");
+        assert_eq!(current["temp_F"], "45");
+        assert_eq!(current["FeelsLikeC"], "0");
+        assert_eq!(current["weatherCode"], "296");
+        assert_eq!(current["weatherDesc"][0]["value"], "Slight rain");
+        assert_eq!(current["winddir16Point"], "SW");
+        assert_eq!(current["pressure"], "1013");
+        assert_eq!(current["pressureInches"], "29.92");
+        assert_eq!(current["observation_time"], "07:15 PM");
+        assert_eq!(
+            weather["nearest_area"][0]["areaName"][0]["value"],
+            "Amsterdam"
+        );
+    }
+
+    #[test]
+    fn converts_days_to_j1() {
+        let weather = to_j1(&make_place(), &make_forecast()).unwrap();
+        let day = &weather["weather"][0];
+        assert_eq!(day["maxtempC"], "9");
+        assert_eq!(day["astronomy"][0]["sunrise"], "08:41 AM");
+        let hourly = day["hourly"].as_array().unwrap();
+        assert_eq!(hourly.len(), 8);
+        assert_eq!(hourly[1]["time"], "300");
+        assert_eq!(hourly[1]["chanceofsnow"], "40");
+    }
+
+    /// A forecast with nothing but the fields `forecast_url` asks for.
+    fn requested_forecast() -> Value {
+        let url = forecast_url(&make_place()).unwrap();
+        let fields = |name: &str| -> Vec<String> {
+            url.query_pairs()
+                .find(|(key, _)| key == name)
+                .map(|(_, fields)| fields.split(',').map(str::to_string).collect())
+                .unwrap_or_default()
+        };
+        let (mut current, mut hourly, mut daily) = (Map::new(), Map::new(), Map::new());
+        current.insert("time".to_string(), json!("2024-01-15T20:15"));
+        for field in fields("current") {
+            let value = if field == "weather_code" { 61.0 } else { 40.0 };
+            current.insert(field, json!(value));
+        }
+        hourly.insert("time".to_string(), json!(["2024-01-15T00:00"]));
+        for field in fields("hourly") {
+            let value = if field == "weather_code" { 61.0 } else { 40.0 };
+            hourly.insert(field, json!([value]));
+        }
+        daily.insert("time".to_string(), json!(["2024-01-15"]));
+        for field in fields("daily") {
+            let value = match field.as_str() {
+                "sunrise" | "sunset" => json!(["2024-01-15T08:41"]),
+                _ => json!([40.0]),
+            };
+            daily.insert(field, value);
+        }
+        json!({"current": current, "hourly": hourly, "daily": daily})
+    }
+
+    #[test]
+    fn requests_every_converted_field() {
+        let weather = to_j1(&make_place(), &requested_forecast()).unwrap();
+        let current = &weather["current_condition"][0];
+        for key in [
+            "temp_C",
+            "FeelsLikeC",
+            "humidity",
+            "weatherCode",
+            "windspeedKmph",
+            "winddirDegree",
+            "pressure",
+            "precipMM",
+            "cloudcover",
+            "visibility",
+            "uvIndex",
+        ] {
+            assert!(current[key].is_string(), "current {} missing", key);
+        }
+        let day = &weather["weather"][0];
+        for key in ["maxtempC", "mintempC"] {
+            assert!(day[key].is_string(), "day {} missing", key);
+        }
+        for key in ["sunrise", "sunset"] {
+            assert!(day["astronomy"][0][key].is_string(), "{} missing", key);
+        }
+        let hour = &day["hourly"][0];
+        for key in [
+            "tempC",
+            "FeelsLikeC",
+            "weatherCode",
+            "precipMM",
+            "chanceofrain",
+        ] {
+            assert!(hour[key].is_string(), "hourly {} missing", key);
+        }
+    }
+
+    #[test]
+    fn parses_coordinates() {
+        assert_eq!(parse_coordinates("52.37,4.89"), Some((52.37, 4.89)));
+        assert_eq!(parse_coordinates("Amsterdam"), None);
+    }
+}
@@ -0,0 +1,130 @@
+use super::Provider;
+use crate::error::Error;
+use crate::http::Http;
+use crate::lang::Lang;
+use crate::location::encode;
+use crate::model::{first_text, Weather};
+
+const FALLBACK_LOCATION: &str = "Oymyakon";
+
+pub struct WttrIn {
+    base_url: Option<String>,
+    lang: Lang,
+}
+
+impl WttrIn {
+    pub fn new(base_url: Option<String>, lang: Lang) -> Self {
+        WttrIn { base_url, lang }
+    }
+
+    /// Build the j1 request URL. The public wttr.in selects the language by
+    /// subdomain; a custom base URL gets it through the `lang=` query parameter.
+    fn weather_url(&self, location: &str) -> String {
+        match &self.base_url {
+            Some(base_url) => format!(
+                "{}/{}?format=j1&lang={}",
+                base_url.trim_end_matches('/'),
+                encode(location),
+                self.lang.code()
+            ),
+            None => format!(
+                "https://{}/{}?format=j1",
+                self.lang.wttr_in_subdomain(),
+                encode(location)
+            ),
+        }
+    }
+}
+
+impl Provider for WttrIn {
+    fn name(&self) -> &'static str {
+        "wttr.in"
+    }
+
+    fn cache_key(&self) -> String {
+        match &self.base_url {
+            Some(base_url) => {
+                let host: String = base_url
+                    .split("://")
+                    .last()
+                    .unwrap_or(base_url)
+                    .trim_end_matches('/')
+                    .chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() || c == '.' {
+                            c
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect();
+                format!("{}-{}", host, self.lang.code())
+            }
+            None => self.lang.wttr_in_subdomain(),
+        }
+    }
+
+    fn fetch(&self, http: &Http, location: &str) -> Result<Weather, Error> {
+        match http.get_json::<Weather>(&self.weather_url(location)) {
+            Err(Error::UnknownLocation(_)) => Err(Error::UnknownLocation(location.to_string())),
+            Ok(weather) if is_fallback(&weather, location) => {
+                Err(Error::UnknownLocation(location.to_string()))
+            }
+            result => result,
+        }
+    }
+}
+
+/// Instead of an error, wttr.in sometimes answers an unknown location with
+/// the weather of its fallback location.
+fn is_fallback(weather: &Weather, location: &str) -> bool {
+    let area = weather.nearest_area();
+    !location.trim().is_empty()
+        && first_text(&area.area_name) == Some(FALLBACK_LOCATION)
+        && !location
+            .to_lowercase()
+            .contains(&FALLBACK_LOCATION.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom(base_url: &str) -> WttrIn {
+        WttrIn::new(Some(base_url.to_string()), Lang::DE)
+    }
+
+    #[test]
+    fn builds_weather_urls() {
+        let public = WttrIn::new(None, Lang::DE);
+        assert_eq!(
+            public.weather_url("São Paulo"),
+            "https://de.wttr.in/S%C3%A3o+Paulo?format=j1"
+        );
+        assert_eq!(
+            custom("http://localhost:8002/").weather_url("São Paulo"),
+            "http://localhost:8002/S%C3%A3o+Paulo?format=j1&lang=de"
+        );
+        assert_eq!(
+            custom("https://example.com/wttr").weather_url(""),
+            "https://example.com/wttr/?format=j1&lang=de"
+        );
+    }
+
+    #[test]
+    fn caches_each_base_url_separately() {
+        assert_eq!(WttrIn::new(None, Lang::DE).cache_key(), "de.wttr.in");
+        assert_eq!(
+            custom("http://localhost:8002/").cache_key(),
+            "localhost_8002-de"
+        );
+        assert_ne!(
+            custom("http://localhost:8002").cache_key(),
+            custom("http://localhost:8003").cache_key()
+        );
+        assert_ne!(
+            custom("http://localhost:8002").cache_key(),
+            WttrIn::new(Some("http://localhost:8002".to_string()), Lang::EN).cache_key()
+        );
+    }
+}
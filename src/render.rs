@@ -0,0 +1,1094 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Locale, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
+use clap::ValueEnum;
+use serde_json::{json, Map, Value};
+
+use crate::alert::Alert;
+use crate::cli::Args;
+use crate::constants::{
+    ALERT_ICON, ALERT_ICON_NERD, INDICATOR_PRESETS, TEMPERATURE_BANDS, WEATHER_CLASSES,
+};
+use crate::error::Error;
+use crate::format::{
+    escape_markup, format_ampm_time, format_chances, format_indicator, format_moon_phase_icon,
+    format_temp, format_time, format_weather_icon, format_wind_direction_icon,
+    get_observation_time, location_now, shown_instant, shown_time,
+};
+use crate::lang::Lang;
+use crate::location::is_mismatch;
+use crate::model::{first_text, text_value, Area, CurrentCondition, Day, Hourly, Keyed, Weather};
+use crate::source::{Forecast, State};
+use crate::template::{self, Template};
+use crate::units::Units;
+
+/// Extra rows for the current conditions in the tooltip, see
+/// `--tooltip-fields`.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TooltipField {
+    Pressure,
+    Uv,
+    Visibility,
+    Precip,
+    Cloudcover,
+    Winddir,
+}
+
+/// What the bar text shows.
+#[derive(Debug, Clone)]
+pub enum Indicator {
+    /// A single `current_condition` key next to the weather icon, like
+    /// `--main-indicator`.
+    Key(String),
+    /// A parsed `--custom-indicator` expression.
+    Expression(Template),
+}
+
+impl Indicator {
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        match &args.custom_indicator {
+            Some(expression) => Template::parse(expression)
+                .map(Self::Expression)
+                .map_err(|err| Error::Config(format!("custom indicator: {}", err))),
+            None => Ok(Self::Key(args.main_indicator.clone())),
+        }
+    }
+
+    /// The indicators SIGUSR2 cycles through in daemon mode, starting with
+    /// the one from [`Indicator::from_args`].
+    pub fn cycle(args: &Args) -> Result<Vec<Self>, Error> {
+        let mut indicators = vec![Self::from_args(args)?];
+        if args.custom_indicator.is_some() {
+            indicators.push(Self::Key(args.main_indicator.clone()));
+        }
+        for preset in INDICATOR_PRESETS {
+            if *preset != args.main_indicator {
+                indicators.push(Self::Key(preset.to_string()));
+            }
+        }
+        Ok(indicators)
+    }
+}
+
+/// Everything parsed from the options that shapes the output, checked once
+/// on startup.
+#[derive(Debug, Clone)]
+pub struct Layout {
+    pub indicators: Vec<Indicator>,
+    pub tooltip_template: Option<Template>,
+    pub alerts: Vec<Alert>,
+}
+
+impl Layout {
+    pub fn from_args(args: &Args) -> Result<Self, Error> {
+        let tooltip_template = args
+            .tooltip_template
+            .as_deref()
+            .map(|source| template::load("tooltip template", source))
+            .transpose()?;
+        let alerts = args
+            .alert
+            .iter()
+            .map(|rule| Alert::parse(rule, HOURS_AHEAD))
+            .collect::<Result<_, _>>()
+            .map_err(|err| Error::Config(format!("alert: {}", err)))?;
+        Ok(Layout {
+            indicators: Indicator::cycle(args)?,
+            tooltip_template,
+            alerts,
+        })
+    }
+}
+
+/// Render the Waybar JSON object (`text`, `tooltip` and `class`). The first
+/// location fills the bar, every other one gets a short section at the end of
+/// the tooltip. The hourly forecast is filtered relative to the current time,
+/// so this should be called again whenever the output needs to be refreshed.
+pub fn render(
+    states: &[State],
+    args: &Args,
+    lang: &Lang,
+    layout: &Layout,
+    indicator: &Indicator,
+) -> Option<Value> {
+    let (first, others) = states.split_first()?;
+    match (&first.forecast, &first.error) {
+        (Some(forecast), _) => Some(render_forecast(
+            forecast, first, others, args, lang, layout, indicator,
+        )),
+        (None, Some(err)) => Some(render_error(err, lang, first.provider)),
+        (None, None) => None,
+    }
+}
+
+fn render_forecast(
+    forecast: &Forecast,
+    state: &State,
+    others: &[State],
+    args: &Args,
+    lang: &Lang,
+    layout: &Layout,
+    indicator: &Indicator,
+) -> Value {
+    let mut data = HashMap::new();
+    let weather = &forecast.weather;
+
+    let current_condition = weather.current_condition();
+    let nearest_area = weather.nearest_area();
+    let units = Units::from_args(args);
+    let now = location_now(&current_condition);
+    let night = is_night_now(weather, now);
+    let weather_icon =
+        format_weather_icon(current_condition.weather_code.as_deref(), args.nerd, night);
+    let current_values = unit_values(current_condition.to_json(), &units);
+    let placeholders = forecast_placeholders(weather, now);
+    let alerts = matching_alerts(&current_values, &placeholders, layout);
+
+    let mut text = match indicator {
+        Indicator::Key(key) => {
+            let indicator = text_value(&current_values[units.key(key)]).unwrap_or_default();
+            if args.vertical_view {
+                format!("{}\n{}", weather_icon, indicator)
+            } else {
+                format!("{} {}", weather_icon, indicator)
+            }
+        }
+        Indicator::Expression(expression) => format_indicator(
+            &current_values,
+            &nearest_area.to_json(),
+            &placeholders,
+            expression,
+            &weather_icon,
+        ),
+    };
+    let mut tooltip = String::new();
+    if !alerts.is_empty() {
+        let icon = if args.nerd {
+            ALERT_ICON_NERD
+        } else {
+            ALERT_ICON
+        };
+        text = if args.vertical_view {
+            format!("{}\n{}", icon, text)
+        } else {
+            format!("{} {}", icon, text)
+        };
+        tooltip += &format!("<b>{}</b>\n", lang.alerts());
+        for alert in &alerts {
+            // Rules are full of `<` and `>`, which Waybar would take for
+            // markup.
+            tooltip += &format!("{} {}: {}\n", icon, alert.name, escape_markup(&alert.rule));
+        }
+        tooltip += "\n";
+    }
+    let mismatch = is_mismatch(&state.location, &nearest_area, args);
+    if mismatch {
+        let icon = if args.nerd {
+            ALERT_ICON_NERD
+        } else {
+            ALERT_ICON
+        };
+        tooltip += &format!(
+            "{} <b>{}</b>: \"{}\" → {}\n\n",
+            icon,
+            lang.location_mismatch(),
+            escape_markup(&state.location),
+            escape_markup(&location_parts(&nearest_area).join(", "))
+        );
+    }
+    data.insert("text", json!(text));
+
+    tooltip += &match &layout.tooltip_template {
+        Some(template) => template.render(&template_data(forecast, args, lang)),
+        None => default_tooltip(forecast, args, lang),
+    };
+    for state in others {
+        tooltip += &format!("\n{}", render_location(state, args, lang));
+    }
+    if let Some(warning) = &forecast.warning {
+        tooltip += &format!("\n{}\n", warning.tooltip(lang, state.provider));
+    }
+    for warning in template_warnings(indicator, layout) {
+        tooltip += &format!("\n{}\n", warning.tooltip(lang, state.provider));
+    }
+    data.insert("tooltip", json!(tooltip));
+
+    let mut classes = if args.legacy_class {
+        vec![legacy_class(&current_condition, lang)]
+    } else {
+        weather_classes(weather, now)
+    };
+    if forecast.stale {
+        classes.push("stale".to_string());
+    }
+    if let Some(warning) = &forecast.warning {
+        classes.push(warning.class().to_string());
+    }
+    if mismatch {
+        classes.push("location-mismatch".to_string());
+    }
+    for alert in alerts {
+        let class = alert.class();
+        if !classes.contains(&class) {
+            classes.push(class);
+        }
+    }
+    data.insert("class", class_value(classes));
+
+    json!(data)
+}
+
+/// The placeholders of the custom indicator and the tooltip template that
+/// didn't parse. They are shown as nothing, so the bar keeps working.
+fn template_warnings(indicator: &Indicator, layout: &Layout) -> Vec<Error> {
+    let indicator = match indicator {
+        Indicator::Expression(template) => Some(("custom indicator", template)),
+        Indicator::Key(_) => None,
+    };
+    let tooltip = layout
+        .tooltip_template
+        .as_ref()
+        .map(|template| ("tooltip template", template));
+    indicator
+        .into_iter()
+        .chain(tooltip)
+        .flat_map(|(option, template)| {
+            template
+                .warnings()
+                .iter()
+                .map(move |warning| Error::Config(format!("{}: {}", option, warning)))
+        })
+        .collect()
+}
+
+/// The condition class from the weather code and its family, `day` or
+/// `night`, and the temperature band, like `rain-light`, `rain`, `day` and
+/// `temp-cold`.
+fn weather_classes(weather: &Weather, now: NaiveDateTime) -> Vec<String> {
+    let current_condition = weather.current_condition();
+    let mut classes = vec![];
+    let code = current_condition
+        .weather_code
+        .as_deref()
+        .and_then(|code| code.parse::<i32>().ok());
+    if let Some((_, condition, family)) = WEATHER_CLASSES.iter().find(|(c, _, _)| Some(*c) == code)
+    {
+        classes.push(condition.to_string());
+        classes.push(family.to_string());
+    }
+    if let Some(night) = upcoming_days(weather, now.date())
+        .first()
+        .and_then(|(_, today)| is_night(today, now.time()))
+    {
+        classes.push(if night { "night" } else { "day" }.to_string());
+    }
+    let temperature = current_condition
+        .temp_c
+        .as_deref()
+        .and_then(|temp| temp.trim().parse::<f64>().ok());
+    if let Some(temperature) = temperature {
+        if let Some((_, band)) = TEMPERATURE_BANDS
+            .iter()
+            .find(|(below, _)| temperature < f64::from(*below))
+        {
+            classes.push(band.to_string());
+        }
+    }
+    classes
+}
+
+/// The class older versions used, from the localized description, like
+/// `light_rain`.
+fn legacy_class(current_condition: &CurrentCondition, lang: &Lang) -> String {
+    lang.weather_desc_value(current_condition)
+        .unwrap_or_default()
+        .to_lowercase()
+        .split(',')
+        .next()
+        .map(|s| s.trim().replace(' ', "_"))
+        .unwrap_or_default()
+}
+
+/// Whether `time` is before sunrise or after sunset on `day`, if both are
+/// known.
+fn is_night(day: &Day, time: NaiveTime) -> Option<bool> {
+    let astronomy = day.astronomy();
+    let parse = |time: Option<&str>| NaiveTime::parse_from_str(time?, "%I:%M %p").ok();
+    let sunrise = parse(astronomy.sunrise.as_deref())?;
+    let sunset = parse(astronomy.sunset.as_deref())?;
+    Some(time < sunrise || time >= sunset)
+}
+
+/// Whether it's night today at `now`, day when sunrise or sunset is unknown.
+fn is_night_now(weather: &Weather, now: NaiveDateTime) -> bool {
+    upcoming_days(weather, now.date())
+        .first()
+        .and_then(|(_, today)| is_night(today, now.time()))
+        .unwrap_or(false)
+}
+
+/// Whether the hourly row at `hour_time` (like `900`) of `day` is at night.
+fn is_night_at(day: &Day, hour_time: &str) -> bool {
+    hour_time
+        .parse::<u32>()
+        .ok()
+        .and_then(|time| NaiveTime::from_hms_opt(time / 100, time % 100, 0))
+        .and_then(|time| is_night(day, time))
+        .unwrap_or(false)
+}
+
+/// The `--alert` rules matching the current conditions (with the
+/// [`unit_values`]) and the [`forecast_placeholders`].
+pub fn matching_alerts<'a>(
+    current_values: &Value,
+    placeholders: &Value,
+    layout: &'a Layout,
+) -> Vec<&'a Alert> {
+    layout
+        .alerts
+        .iter()
+        .filter(|alert| alert.matches(current_values, placeholders))
+        .collect()
+}
+
+/// The built-in tooltip: current conditions, then a block per day with its
+/// hourly forecast.
+fn default_tooltip(forecast: &Forecast, args: &Args, lang: &Lang) -> String {
+    let weather = &forecast.weather;
+    let current_condition = weather.current_condition();
+    let nearest_area = weather.nearest_area();
+    let units = Units::from_args(args);
+    let feels_like = units.temperature(
+        current_condition.feels_like_c.as_deref(),
+        current_condition.feels_like_f.as_deref(),
+    );
+    let temp = units.temperature(
+        current_condition.temp_c.as_deref(),
+        current_condition.temp_f.as_deref(),
+    );
+    let mut tooltip = String::new();
+    if forecast.stale {
+        tooltip += &format!("<i>{}</i>\n", format_as_of(forecast, args, lang));
+    }
+    tooltip += &format!(
+        "<b>{}</b> {}°\n",
+        escape_markup(
+            &lang
+                .weather_desc_value(&current_condition)
+                .unwrap_or_default()
+        ),
+        temp.as_deref().unwrap_or("?"),
+    );
+    if let Some(feels_like) = feels_like {
+        tooltip += &format!("{}: {}°\n", lang.feels_like(), feels_like);
+    }
+    if let Some(wind) = units.wind(
+        current_condition.windspeed_kmph.as_deref(),
+        current_condition.windspeed_miles.as_deref(),
+    ) {
+        tooltip += &format!("{}: {}\n", lang.wind(), wind);
+    }
+    if let Some(humidity) = &current_condition.humidity {
+        tooltip += &format!("{}: {}%\n", lang.humidity(), humidity);
+    }
+    for field in &args.tooltip_fields {
+        if let Some((label, value)) = tooltip_field(*field, &current_condition, &units, args, lang)
+        {
+            tooltip += &format!("{}: {}\n", label, value);
+        }
+    }
+
+    let location_parts = location_parts(&nearest_area);
+    if !location_parts.is_empty() {
+        tooltip += &format!("{}: {}\n", lang.location(), location_parts.join(", "));
+    }
+
+    if args.observation_time {
+        if let Some(formatted_time) =
+            get_observation_time(&current_condition, args.ampm, args.local_time)
+        {
+            tooltip += &format!("{}: {}\n", lang.observation_time(), formatted_time);
+        }
+    }
+
+    let now = location_now(&current_condition);
+    for (i, (date, day)) in upcoming_days(weather, now.date()).iter().enumerate() {
+        tooltip += "\n<b>";
+        if i == 0 {
+            tooltip += &format!("{}, ", lang.today());
+        }
+        if i == 1 {
+            tooltip += &format!("{}, ", lang.tomorrow());
+        }
+        let locale = Locale::try_from(lang.locale_str().as_str()).unwrap_or(Locale::en_US);
+        tooltip += &format!(
+            "{}</b>\n",
+            date.format_localized(args.date_format.as_str(), locale)
+        );
+
+        let max_temp = units.temperature(day.maxtemp_c.as_deref(), day.maxtemp_f.as_deref());
+        let min_temp = units.temperature(day.mintemp_c.as_deref(), day.mintemp_f.as_deref());
+
+        tooltip += &format!(
+            "{} {}° {} {}° ",
+            if args.nerd { "󰳡" } else { "⬆️" },
+            max_temp.as_deref().unwrap_or("?"),
+            if args.nerd { "󰳛" } else { "⬇️" },
+            min_temp.as_deref().unwrap_or("?")
+        );
+
+        let astronomy = day.astronomy();
+        let moon_phase = astronomy.moon_phase.as_deref().unwrap_or("");
+        let moon_illumination = astronomy.moon_illumination.as_deref().unwrap_or("?");
+        let sunrise = astronomy
+            .sunrise
+            .as_deref()
+            .and_then(|time| format_day_time(*date, time, &current_condition, args));
+        let sunset = astronomy
+            .sunset
+            .as_deref()
+            .and_then(|time| format_day_time(*date, time, &current_condition, args));
+
+        tooltip += &format!(
+            "{} {} {} {} {} {}%\n",
+            if args.nerd { "󰖜" } else { "🌅" },
+            sunrise.as_deref().unwrap_or("?"),
+            if args.nerd { "󰖛" } else { "🌇" },
+            sunset.as_deref().unwrap_or("?"),
+            format_moon_phase_icon(moon_phase, args.nerd),
+            moon_illumination
+        );
+
+        for (hour_time, hour) in upcoming_hours(day, i == 0, now) {
+            let feels_like =
+                units.temperature(hour.feels_like_c.as_deref(), hour.feels_like_f.as_deref());
+            let sep = if args.tabs { "\t" } else { " " };
+            let mut tooltip_line = format!(
+                "{}{sep}{}{sep}{}{sep}{}",
+                format_hour(*date, hour_time, &current_condition, args),
+                format_weather_icon(
+                    hour.weather_code.as_deref(),
+                    args.nerd,
+                    is_night_at(day, hour_time)
+                ),
+                format_temp(feels_like.as_deref().unwrap_or("?")),
+                escape_markup(&lang.weather_desc_value(hour).unwrap_or_default()),
+            );
+            if !args.hide_conditions {
+                tooltip_line += format!(", {}", format_chances(hour, lang)).as_str();
+            }
+            tooltip_line += "\n";
+            tooltip += &tooltip_line;
+        }
+    }
+    tooltip
+}
+
+fn tooltip_field(
+    field: TooltipField,
+    current_condition: &CurrentCondition,
+    units: &Units,
+    args: &Args,
+    lang: &Lang,
+) -> Option<(String, String)> {
+    let c = current_condition;
+    Some(match field {
+        TooltipField::Pressure => (
+            lang.pressure(),
+            units.pressure(c.pressure.as_deref(), c.pressure_inches.as_deref())?,
+        ),
+        TooltipField::Uv => (lang.uv_index(), c.uv_index.clone()?),
+        TooltipField::Visibility => (
+            lang.visibility(),
+            units.visibility(c.visibility.as_deref(), c.visibility_miles.as_deref())?,
+        ),
+        TooltipField::Precip => (
+            lang.precipitation(),
+            units.precipitation(c.precip_mm.as_deref(), c.precip_inches.as_deref())?,
+        ),
+        TooltipField::Cloudcover => (lang.cloud_cover(), format!("{}%", c.cloudcover.as_deref()?)),
+        TooltipField::Winddir => {
+            let arrow = c
+                .winddir_degree
+                .as_deref()
+                .and_then(|degree| format_wind_direction_icon(degree, args.nerd));
+            let direction = match (arrow, c.winddir_16_point.as_deref()) {
+                (Some(arrow), Some(point)) => format!("{} {}", arrow, point),
+                (Some(arrow), None) => arrow.to_string(),
+                (None, point) => point?.to_string(),
+            };
+            (lang.wind_direction(), direction)
+        }
+    })
+}
+
+/// The values a `--tooltip-template` can use: the `current_condition` and
+/// `nearest_area` keys, the same computed values the default tooltip shows,
+/// and a `days` list whose items hold the day and astronomy keys and an
+/// `hours` list.
+fn template_data(forecast: &Forecast, args: &Args, lang: &Lang) -> Value {
+    let weather = &forecast.weather;
+    let current_condition = weather.current_condition();
+    let nearest_area = weather.nearest_area();
+    let units = Units::from_args(args);
+    let now = location_now(&current_condition);
+    let locale = Locale::try_from(lang.locale_str().as_str()).unwrap_or(Locale::en_US);
+
+    let mut days = vec![];
+    for (i, (date, day)) in upcoming_days(weather, now.date()).iter().enumerate() {
+        let mut hours = vec![];
+        for (hour_time, hour) in upcoming_hours(day, i == 0, now) {
+            let mut values = unit_values(hour.to_json(), &units);
+            values["TIME"] =
+                json!(format_hour(*date, hour_time, &current_condition, args).trim_end());
+            values["ICON"] = json!(format_weather_icon(
+                hour.weather_code.as_deref(),
+                args.nerd,
+                is_night_at(day, hour_time)
+            ));
+            values["DESC"] = json!(escape_markup(
+                &lang.weather_desc_value(hour).unwrap_or_default()
+            ));
+            values["CHANCES"] = json!(format_chances(hour, lang));
+            hours.push(values);
+        }
+
+        let astronomy = day.astronomy();
+        let mut values = day.to_json();
+        if let (Value::Object(values), Ok(Value::Object(astronomy))) =
+            (&mut values, serde_json::to_value(&astronomy))
+        {
+            values.extend(astronomy);
+        }
+        values["DAY"] = json!(match i {
+            0 => lang.today(),
+            1 => lang.tomorrow(),
+            _ => String::new(),
+        });
+        values["DATE"] = json!(date
+            .format_localized(args.date_format.as_str(), locale)
+            .to_string());
+        values["MAX"] =
+            json!(units.temperature(day.maxtemp_c.as_deref(), day.maxtemp_f.as_deref()));
+        values["MIN"] =
+            json!(units.temperature(day.mintemp_c.as_deref(), day.mintemp_f.as_deref()));
+        values["SUNRISE"] = json!(astronomy
+            .sunrise
+            .as_deref()
+            .and_then(|time| format_day_time(*date, time, &current_condition, args)));
+        values["SUNSET"] = json!(astronomy.sunset.as_deref().and_then(|time| format_day_time(
+            *date,
+            time,
+            &current_condition,
+            args
+        )));
+        values["MOON"] = json!(format_moon_phase_icon(
+            astronomy.moon_phase.as_deref().unwrap_or(""),
+            args.nerd
+        ));
+        values["hours"] = json!(hours);
+        days.push(values);
+    }
+
+    let mut values = unit_values(current_condition.to_json(), &units);
+    if let (Value::Object(values), Value::Object(area)) = (&mut values, nearest_area.to_json()) {
+        values.extend(area);
+    }
+    values["ICON"] = json!(format_weather_icon(
+        current_condition.weather_code.as_deref(),
+        args.nerd,
+        is_night_now(weather, now)
+    ));
+    values["DESC"] = json!(escape_markup(
+        &lang
+            .weather_desc_value(&current_condition)
+            .unwrap_or_default()
+    ));
+    values["LOCATION"] = json!(escape_markup(&location_parts(&nearest_area).join(", ")));
+    values["OBSERVATION_TIME"] = json!(get_observation_time(
+        &current_condition,
+        args.ampm,
+        args.local_time
+    ));
+    values["AS_OF"] = json!(if forecast.stale {
+        format_as_of(forecast, args, lang)
+    } else {
+        String::new()
+    });
+    values["days"] = json!(days);
+    if let (Value::Object(values), Value::Object(forecast)) =
+        (&mut values, forecast_placeholders(weather, now))
+    {
+        values.extend(forecast);
+    }
+    values
+}
+
+/// Add the unit aware values to the j1 keys of a `current_condition` or an
+/// hourly row: `TEMP`, `FEELS_LIKE`, `WIND`, `PRESSURE`, `PRECIP` and
+/// `VISIBILITY`, plus the `windspeedMs` and `windspeedKnots` keys wttr.in
+/// lacks.
+pub fn unit_values(mut values: Value, units: &Units) -> Value {
+    let get = |key: &str| text_value(&values[key]).map(str::to_string);
+    let temp_c = get("temp_C").or_else(|| get("tempC"));
+    let temp_f = get("temp_F").or_else(|| get("tempF"));
+    let computed = [
+        (
+            "TEMP",
+            units.temperature(temp_c.as_deref(), temp_f.as_deref()),
+        ),
+        (
+            "FEELS_LIKE",
+            units.temperature(get("FeelsLikeC").as_deref(), get("FeelsLikeF").as_deref()),
+        ),
+        (
+            "WIND",
+            units.wind(
+                get("windspeedKmph").as_deref(),
+                get("windspeedMiles").as_deref(),
+            ),
+        ),
+        (
+            "PRESSURE",
+            units.pressure(get("pressure").as_deref(), get("pressureInches").as_deref()),
+        ),
+        (
+            "PRECIP",
+            units.precipitation(get("precipMM").as_deref(), get("precipInches").as_deref()),
+        ),
+        (
+            "VISIBILITY",
+            units.visibility(
+                get("visibility").as_deref(),
+                get("visibilityMiles").as_deref(),
+            ),
+        ),
+    ];
+    let wind_keys = Units::wind_keys(get("windspeedKmph").as_deref());
+    if let Value::Object(map) = &mut values {
+        for (key, value) in computed {
+            map.insert(key.to_string(), json!(value));
+        }
+        for (key, value) in wind_keys {
+            map.insert(key.to_string(), json!(value));
+        }
+    }
+    values
+}
+
+/// How far ahead `hour+N.` placeholders reach.
+pub const HOURS_AHEAD: i64 = 48;
+
+/// The `today.`, `tomorrow.`, `astro.`, `next_hour.` and `hour+N.` namespaces
+/// for placeholders. `hour+N` is the hourly row in effect N hours from `now`.
+pub fn forecast_placeholders(weather: &Weather, now: NaiveDateTime) -> Value {
+    let days = upcoming_days(weather, now.date());
+    let mut values = Map::new();
+    if let Some((_, today)) = days.first() {
+        values.insert("today".to_string(), today.to_json());
+        values.insert("astro".to_string(), json!(today.astronomy()));
+    }
+    if let Some((_, tomorrow)) = days.get(1) {
+        values.insert("tomorrow".to_string(), tomorrow.to_json());
+    }
+
+    let rows: Vec<(NaiveDateTime, &Hourly)> = days
+        .iter()
+        .flat_map(|(date, day)| {
+            day.hourly.iter().filter_map(move |hour| {
+                let time = hour.time.as_deref()?.parse::<u32>().ok()?;
+                Some((date.and_hms_opt(time / 100, time % 100, 0)?, hour))
+            })
+        })
+        .collect();
+    let Some(last_date) = days.last().map(|(date, _)| *date) else {
+        return Value::Object(values);
+    };
+    for hours in 0..=HOURS_AHEAD {
+        let target = now + chrono::Duration::hours(hours);
+        if target.date() > last_date {
+            break;
+        }
+        if let Some((_, hour)) = rows.iter().rev().find(|(start, _)| *start <= target) {
+            values.insert(format!("hour+{}", hours), hour.to_json());
+            if hours == 1 {
+                values.insert("next_hour".to_string(), hour.to_json());
+            }
+        }
+    }
+    Value::Object(values)
+}
+
+/// The forecast days from `today` on.
+fn upcoming_days(weather: &Weather, today: NaiveDate) -> Vec<(NaiveDate, &Day)> {
+    weather
+        .weather
+        .iter()
+        .filter_map(|day| {
+            let date = NaiveDate::parse_from_str(day.date.as_deref()?, "%Y-%m-%d").ok()?;
+            Some((date, day))
+        })
+        .filter(|(date, _)| *date >= today)
+        .collect()
+}
+
+/// The hourly rows of `day` with their time, leaving out the ones that are
+/// more than two hours in the past today.
+fn upcoming_hours(
+    day: &Day,
+    today: bool,
+    now: NaiveDateTime,
+) -> impl Iterator<Item = (&str, &Hourly)> {
+    day.hourly.iter().filter_map(move |hour| {
+        let hour_time = hour.time.as_deref()?;
+        let formatted_hour_time = if hour_time.len() >= 2 {
+            &hour_time[..hour_time.len() - 2]
+        } else {
+            hour_time
+        };
+        if today
+            && now.hour() >= 2
+            && formatted_hour_time.parse::<u32>().unwrap_or(0) < now.hour() - 2
+        {
+            return None;
+        }
+        Some((hour_time, hour))
+    })
+}
+
+/// The tooltip section of an additional location: its name and the current
+/// conditions on a single line.
+fn render_location(state: &State, args: &Args, lang: &Lang) -> String {
+    let Some(forecast) = &state.forecast else {
+        let summary = state
+            .error
+            .as_ref()
+            .map(|err| err.tooltip(lang, state.provider))
+            .unwrap_or_default();
+        return format!("<b>{}</b>\n{}\n", escape_markup(&state.location), summary);
+    };
+    let current_condition = forecast.weather.current_condition();
+    let nearest_area = forecast.weather.nearest_area();
+
+    let mut name = escape_markup(
+        location_parts(&nearest_area)
+            .first()
+            .unwrap_or(&state.location.as_str()),
+    );
+    if is_mismatch(&state.location, &nearest_area, args) {
+        name += &format!(
+            " {}",
+            if args.nerd {
+                ALERT_ICON_NERD
+            } else {
+                ALERT_ICON
+            }
+        );
+    }
+    if forecast.stale {
+        name += &format!(" <i>({})</i>", format_as_of(forecast, args, lang));
+    }
+
+    let units = Units::from_args(args);
+    let temp = units.temperature(
+        current_condition.temp_c.as_deref(),
+        current_condition.temp_f.as_deref(),
+    );
+    let feels_like = units.temperature(
+        current_condition.feels_like_c.as_deref(),
+        current_condition.feels_like_f.as_deref(),
+    );
+    let mut line = format!(
+        "{} {} {}°",
+        format_weather_icon(
+            current_condition.weather_code.as_deref(),
+            args.nerd,
+            is_night_now(&forecast.weather, location_now(&current_condition))
+        ),
+        escape_markup(
+            &lang
+                .weather_desc_value(&current_condition)
+                .unwrap_or_default()
+        ),
+        temp.as_deref().unwrap_or("?"),
+    );
+    if let Some(feels_like) = feels_like {
+        line += &format!(", {}: {}°", lang.feels_like(), feels_like);
+    }
+    if let Some(wind) = units.wind(
+        current_condition.windspeed_kmph.as_deref(),
+        current_condition.windspeed_miles.as_deref(),
+    ) {
+        line += &format!(", {}: {}", lang.wind(), wind);
+    }
+    if let Some(humidity) = &current_condition.humidity {
+        line += &format!(", {}: {}%", lang.humidity(), humidity);
+    }
+    format!("<b>{}</b>\n{}\n", name, line)
+}
+
+fn format_as_of(forecast: &Forecast, args: &Args, lang: &Lang) -> String {
+    let fetched_at = shown_instant(
+        &forecast.weather.current_condition(),
+        DateTime::<Utc>::from(forecast.fetched_at),
+        args.local_time,
+    );
+    format!(
+        "{} {}",
+        lang.as_of(),
+        fetched_at.format(if args.ampm { "%I:%M %p" } else { "%H:%M" })
+    )
+}
+
+/// The label of an hourly row on `date`, in the time zone times are shown in.
+fn format_hour(
+    date: NaiveDate,
+    hour_time: &str,
+    current_condition: &CurrentCondition,
+    args: &Args,
+) -> String {
+    let time = hour_time.trim().parse::<u32>().unwrap_or_default();
+    let Some(time) = date.and_hms_opt(time / 100, time % 100, 0) else {
+        return format_time(hour_time, args.ampm);
+    };
+    let time = shown_time(current_condition, time, args.local_time);
+    format_time(&(time.hour() * 100 + time.minute()).to_string(), args.ampm)
+}
+
+/// A time of day on `date` like `07:45 AM`, such as sunrise, in the time zone
+/// times are shown in.
+fn format_day_time(
+    date: NaiveDate,
+    time: &str,
+    current_condition: &CurrentCondition,
+    args: &Args,
+) -> Option<String> {
+    let time = NaiveTime::parse_from_str(time, "%I:%M %p").ok()?;
+    let time = shown_time(current_condition, date.and_time(time), args.local_time);
+    format_ampm_time(&time.format("%I:%M %p").to_string(), args.ampm)
+}
+
+pub fn location_parts(nearest_area: &Area) -> Vec<&str> {
+    [
+        first_text(&nearest_area.area_name),
+        first_text(&nearest_area.region),
+        first_text(&nearest_area.country),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|part| !part.is_empty())
+    .collect()
+}
+
+/// Waybar accepts a list of classes, but a single one is kept a plain string
+/// for existing setups.
+fn class_value(mut classes: Vec<String>) -> Value {
+    if classes.len() == 1 {
+        json!(classes.remove(0))
+    } else {
+        json!(classes)
+    }
+}
+
+pub fn render_error(err: &Error, lang: &Lang, provider: &str) -> Value {
+    json!({
+        "text": "⛓️‍💥",
+        "tooltip": err.tooltip(lang, provider),
+        "class": ["error", err.class()],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Local, TimeZone};
+    use std::time::{Duration, SystemTime};
+
+    fn weather(code: &str, temp: &str) -> Weather {
+        serde_json::from_value(json!({
+            "current_condition": [{"weatherCode": code, "temp_C": temp}],
+            "weather": [{
+                "date": Local::now().date_naive().format("%Y-%m-%d").to_string(),
+                "astronomy": [{"sunrise": "07:45 AM", "sunset": "05:12 PM"}],
+                "hourly": []
+            }]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn derives_classes_from_the_weather_code() {
+        let today = Local::now().date_naive();
+        let noon = today.and_hms_opt(12, 0, 0).unwrap();
+        let night = today.and_hms_opt(22, 0, 0).unwrap();
+        assert_eq!(
+            weather_classes(&weather("296", "7"), noon),
+            ["rain-light", "rain", "day", "temp-cold"]
+        );
+        assert_eq!(
+            weather_classes(&weather("338", "-3"), night),
+            ["snow-heavy", "snow", "night", "temp-freezing"]
+        );
+        assert_eq!(
+            weather_classes(&weather("389", "12"), noon),
+            ["thunder", "thunder", "day", "temp-mild"]
+        );
+        assert_eq!(
+            weather_classes(&weather("431", "12"), noon),
+            ["rain-light", "rain", "day", "temp-mild"]
+        );
+        assert_eq!(
+            weather_classes(&weather("999", "30"), noon),
+            ["day", "temp-hot"]
+        );
+    }
+
+    #[test]
+    fn marks_stale_forecasts() {
+        use clap::Parser;
+        let args = Args::parse_from(["wttrbar"]);
+        let layout = Layout::from_args(&args).unwrap();
+        let fetched_at = SystemTime::now() - Duration::from_secs(2 * 60 * 60);
+        let state = State {
+            location: "Amsterdam".to_string(),
+            provider: "wttr.in",
+            forecast: Some(Forecast {
+                weather: weather("296", "7"),
+                fetched_at,
+                stale: true,
+                warning: None,
+            }),
+            error: Some(Error::Network(crate::error::NetworkError::Timeout)),
+            loaded_at: None,
+            failures: 1,
+        };
+
+        let output = render(&[state], &args, &Lang::EN, &layout, &layout.indicators[0]).unwrap();
+        let classes = output["class"].as_array().unwrap();
+        assert!(classes.contains(&json!("stale")));
+        let as_of = format!(
+            "<i>As of {}</i>",
+            DateTime::<Local>::from(fetched_at).format("%H:%M")
+        );
+        assert!(output["tooltip"].as_str().unwrap().contains(&as_of));
+    }
+
+    #[test]
+    fn warns_about_invalid_custom_indicator_placeholders() {
+        use clap::Parser;
+        let args = Args::parse_from(["wttrbar", "--custom-indicator", "{temp_C}°{ }"]);
+        let layout = Layout::from_args(&args).unwrap();
+        let state = State {
+            location: "Amsterdam".to_string(),
+            provider: "wttr.in",
+            forecast: Some(Forecast {
+                weather: weather("296", "7"),
+                fetched_at: SystemTime::now(),
+                stale: false,
+                warning: None,
+            }),
+            error: None,
+            loaded_at: None,
+            failures: 0,
+        };
+
+        let output = render(&[state], &args, &Lang::EN, &layout, &layout.indicators[0]).unwrap();
+        assert_eq!(output["text"], "7°");
+        let tooltip = output["tooltip"].as_str().unwrap();
+        assert!(tooltip.contains("custom indicator: empty placeholder at position 10"));
+        assert!(!output["class"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("error-config")));
+    }
+
+    #[test]
+    fn escapes_descriptions_in_the_tooltip() {
+        use clap::Parser;
+        let args = Args::parse_from(["wttrbar"]);
+        let desc = json!([{"value": "Rain & <snow>"}]);
+        let day = |date: chrono::NaiveDate| {
+            json!({
+                "date": date.format("%Y-%m-%d").to_string(),
+                "hourly": [{"time": "1200", "weatherCode": "296", "weatherDesc": desc}]
+            })
+        };
+        let today = Local::now().date_naive();
+        let weather = serde_json::from_value(json!({
+            "current_condition": [{"weatherCode": "296", "temp_C": "7", "weatherDesc": desc}],
+            "weather": [day(today), day(today.succ_opt().unwrap())]
+        }))
+        .unwrap();
+        let forecast = Forecast {
+            weather,
+            fetched_at: SystemTime::now(),
+            stale: false,
+            warning: None,
+        };
+
+        let tooltip = default_tooltip(&forecast, &args, &Lang::EN);
+        // The header and at least tomorrow's row.
+        assert!(!tooltip.contains("<snow>"));
+        assert!(tooltip.matches("Rain &amp; &lt;snow&gt;").count() >= 2);
+    }
+
+    #[test]
+    fn escapes_extra_locations() {
+        use clap::Parser;
+        let args = Args::parse_from(["wttrbar"]);
+        let state = |forecast| State {
+            location: "Rock & Roll <Hall>".to_string(),
+            provider: "wttr.in",
+            forecast,
+            error: Some(Error::UnknownLocation("Rock & Roll <Hall>".to_string())),
+            loaded_at: None,
+            failures: 1,
+        };
+
+        let failed = render_location(&state(None), &args, &Lang::EN);
+        assert!(failed.starts_with("<b>Rock &amp; Roll &lt;Hall&gt;</b>\n"));
+        let forecast = Forecast {
+            weather: weather("296", "7"),
+            fetched_at: SystemTime::now(),
+            stale: false,
+            warning: None,
+        };
+        let loaded = render_location(&state(Some(forecast)), &args, &Lang::EN);
+        assert!(loaded.starts_with("<b>Rock &amp; Roll &lt;Hall&gt;</b>\n"));
+    }
+
+    #[test]
+    fn converts_only_the_shown_times_with_local_time() {
+        use clap::Parser;
+        let tokyo: CurrentCondition = serde_json::from_value(json!({
+            "observation_time": "11:45 PM",
+            "localObsDateTime": "2026-10-19 08:45 AM"
+        }))
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+        let on_machine = |hour| {
+            FixedOffset::east_opt(9 * 3600)
+                .unwrap()
+                .from_local_datetime(&date.and_hms_opt(hour, 0, 0).unwrap())
+                .unwrap()
+                .with_timezone(&Local)
+        };
+
+        let args = Args::parse_from(["wttrbar"]);
+        assert_eq!(format_hour(date, "900", &tokyo, &args), "09");
+        assert_eq!(
+            format_day_time(date, "05:58 AM", &tokyo, &args).as_deref(),
+            Some("05:58")
+        );
+
+        let args = Args::parse_from(["wttrbar", "--local-time"]);
+        assert_eq!(
+            format_hour(date, "900", &tokyo, &args),
+            format_time(&on_machine(9).format("%-H%M").to_string(), false)
+        );
+        assert_eq!(
+            format_day_time(date, "06:00 AM", &tokyo, &args),
+            Some(on_machine(6).format("%H:%M").to_string())
+        );
+    }
+}
@@ -0,0 +1,368 @@
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::cache::{self, Cache};
+use crate::error::Error;
+use crate::geoclue;
+use crate::http::Http;
+use crate::model::Weather;
+use crate::provider::Provider;
+
+/// How long the daemon waits before loading a failed location again. It
+/// doubles with every failure in a row, up to `--interval`.
+const RETRY_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The weather of one location: a provider and the cache in front of it.
+pub struct Source {
+    provider: Box<dyn Provider>,
+    location: String,
+    cache: Option<Cache>,
+    cache_name: String,
+    geoclue_precision: u8,
+}
+
+/// A loaded forecast and when it was fetched.
+pub struct Forecast {
+    pub weather: Weather,
+    pub fetched_at: SystemTime,
+    /// Set when fetching failed and an older forecast is shown instead.
+    pub stale: bool,
+    /// A problem that didn't keep the forecast from being shown.
+    pub warning: Option<Error>,
+}
+
+/// What is known about one location in a long running process: the last
+/// forecast, the last error, or both.
+pub struct State {
+    pub location: String,
+    pub provider: &'static str,
+    pub forecast: Option<Forecast>,
+    pub error: Option<Error>,
+    /// When the location was last loaded, and how often in a row that
+    /// failed.
+    pub loaded_at: Option<Instant>,
+    pub failures: u32,
+}
+
+impl State {
+    /// Take the result of [`Source::load`]. When loading failed, the previous
+    /// forecast is kept as stale until it's older than `max_age`.
+    pub fn update(&mut self, result: Result<Forecast, Error>, max_age: Duration) {
+        self.loaded_at = Some(Instant::now());
+        match result {
+            Ok(forecast) => {
+                self.forecast = Some(forecast);
+                self.error = None;
+                self.failures = 0;
+            }
+            Err(err) => {
+                self.failures = self.failures.saturating_add(1);
+                if let Some(forecast) = &mut self.forecast {
+                    forecast.stale = true;
+                    if forecast.fetched_at.elapsed().unwrap_or_default() > max_age {
+                        self.forecast = None;
+                    }
+                }
+                self.error = Some(err);
+            }
+        }
+    }
+
+    /// Whether the location should be loaded again: every `interval`, or
+    /// sooner with backoff after a failure.
+    pub fn is_due(&self, interval: Duration) -> bool {
+        self.loaded_at
+            .is_none_or(|loaded_at| loaded_at.elapsed() >= self.wait(interval))
+    }
+
+    fn wait(&self, interval: Duration) -> Duration {
+        if self.failures == 0 {
+            return interval;
+        }
+        RETRY_BACKOFF
+            .saturating_mul(2u32.saturating_pow(self.failures - 1))
+            .min(interval)
+    }
+}
+
+impl Source {
+    /// `geoclue_precision` rounds the position when `location` is
+    /// `auto-geoclue`.
+    pub fn new(
+        provider: Box<dyn Provider>,
+        location: String,
+        cache: Option<Cache>,
+        geoclue_precision: u8,
+    ) -> Self {
+        let cache_name = cache::file_name(&provider.cache_key(), &location);
+        Source {
+            provider,
+            location,
+            cache,
+            cache_name,
+            geoclue_precision,
+        }
+    }
+
+    pub fn state(&self) -> State {
+        State {
+            location: self.location.clone(),
+            provider: self.provider.name(),
+            forecast: None,
+            error: None,
+            loaded_at: None,
+            failures: 0,
+        }
+    }
+
+    /// Read the cached forecast if it is recent enough, otherwise fetch and
+    /// cache a new one. `refresh` skips the cache. When fetching fails, an
+    /// outdated cache within `--cache-max-age` is returned as stale.
+    pub fn load(&self, http: &Http, refresh: bool) -> Result<Forecast, Error> {
+        if let Some(cache) = &self.cache {
+            if !refresh {
+                if let Some((weather, fetched_at)) = cache.read(&self.cache_name) {
+                    return Ok(self.forecast(weather, fetched_at));
+                }
+            }
+        }
+
+        let weather = match self.fetch(http) {
+            Ok(weather) => weather,
+            Err(err) => {
+                return match self
+                    .cache
+                    .as_ref()
+                    .and_then(|cache| cache.read_stale(&self.cache_name))
+                {
+                    Some((weather, fetched_at)) => Ok(Forecast {
+                        stale: true,
+                        ..self.forecast(weather, fetched_at)
+                    }),
+                    None => Err(err),
+                }
+            }
+        };
+        let mut forecast = self.forecast(weather, SystemTime::now());
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.write(&self.cache_name, &forecast.weather) {
+                forecast.warning = Some(Error::CacheIo(err));
+            }
+        }
+        Ok(forecast)
+    }
+
+    fn fetch(&self, http: &Http) -> Result<Weather, Error> {
+        let weather = self.provider.fetch(http, &self.resolve_location())?;
+        if weather.current_condition.is_empty() {
+            return Err(Error::MissingField("current_condition"));
+        }
+        Ok(weather)
+    }
+
+    /// `auto-geoclue` is looked up on every fetch, so a long running daemon
+    /// follows the device. Without a position from GeoClue2, the provider
+    /// falls back to the IP address.
+    fn resolve_location(&self) -> String {
+        if self.location != geoclue::AUTO_GEOCLUE {
+            return self.location.clone();
+        }
+        geoclue::locate(self.geoclue_precision).unwrap_or_else(|err| {
+            eprintln!("wttrbar: GeoClue2: {}, using IP geolocation", err);
+            String::new()
+        })
+    }
+
+    fn forecast(&self, weather: Weather, fetched_at: SystemTime) -> Forecast {
+        Forecast {
+            weather,
+            fetched_at,
+            stale: false,
+            warning: None,
+        }
+    }
+}
+
+/// Load all `sources` concurrently, see [`Source::load`].
+pub fn load_all<'a>(
+    sources: impl IntoIterator<Item = &'a Source>,
+    http: &Http,
+    refresh: bool,
+) -> Vec<Result<Forecast, Error>> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = sources
+            .into_iter()
+            .map(|source| scope.spawn(move || source.load(http, refresh)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Loading the weather panicked"))
+            .collect()
+    })
+}
+
+/// `--location` can be repeated, one location per value. Commas belong to
+/// the location, as in `Berlin, Germany` or `52.37,4.89`. Without any
+/// location, wttr.in picks one based on the IP address.
+pub fn collect_locations(values: &[String]) -> Vec<String> {
+    let mut locations: Vec<String> = values
+        .iter()
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect();
+    if locations.is_empty() {
+        locations.push(String::new());
+    }
+    locations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use crate::error::NetworkError;
+    use clap::Parser;
+    use std::env;
+    use std::fs;
+
+    /// Returns a fixed forecast, or fails like an unreachable server.
+    struct Fake {
+        fail: bool,
+    }
+
+    impl Provider for Fake {
+        fn name(&self) -> &'static str {
+            "Fake"
+        }
+
+        fn cache_key(&self) -> String {
+            "fake".to_string()
+        }
+
+        fn fetch(&self, _http: &Http, _location: &str) -> Result<Weather, Error> {
+            if self.fail {
+                return Err(NetworkError::Unreachable.into());
+            }
+            Ok(serde_json::from_value(serde_json::json!({
+                "current_condition": [{"temp_C": "7"}]
+            }))
+            .unwrap())
+        }
+    }
+
+    fn source(dir: &std::path::Path, fail: bool, ttl: u64, max_age: u64) -> Source {
+        let cache = Cache::new(
+            dir.to_path_buf(),
+            Duration::from_secs(ttl),
+            Duration::from_secs(max_age),
+        );
+        Source::new(
+            Box::new(Fake { fail }),
+            "Amsterdam".to_string(),
+            Some(cache),
+            2,
+        )
+    }
+
+    #[test]
+    fn falls_back_to_the_cache_until_max_age() {
+        let dir = env::temp_dir().join(format!("wttrbar-test-{}", std::process::id()));
+        let http = Http::new(&Args::parse_from(["wttrbar"]));
+
+        let fresh = source(&dir, false, 3600, 86400).load(&http, true).unwrap();
+        assert!(!fresh.stale);
+        thread::sleep(Duration::from_millis(20));
+
+        // Within the TTL, the cache answers and the failing provider isn't
+        // asked.
+        let cached = source(&dir, true, 3600, 86400).load(&http, false).unwrap();
+        assert!(!cached.stale);
+
+        // Past the TTL, a failed fetch falls back to the old forecast.
+        let stale = source(&dir, true, 0, 86400).load(&http, false).unwrap();
+        assert!(stale.stale);
+        assert_eq!(
+            stale.weather.current_condition().temp_c.as_deref(),
+            Some("7")
+        );
+
+        // Past the maximum age, the error comes through.
+        let err = source(&dir, true, 0, 0).load(&http, false);
+        assert!(matches!(
+            err,
+            Err(Error::Network(NetworkError::Unreachable))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_the_previous_forecast_as_stale() {
+        let dir = env::temp_dir();
+        let mut state = source(&dir, true, 0, 0).state();
+        let forecast = |age: u64| Forecast {
+            weather: Weather::default(),
+            fetched_at: SystemTime::now() - Duration::from_secs(age),
+            stale: false,
+            warning: None,
+        };
+        let failed = || Err(NetworkError::Timeout.into());
+
+        state.update(Ok(forecast(60)), Duration::from_secs(3600));
+        state.update(failed(), Duration::from_secs(3600));
+        assert!(state
+            .forecast
+            .as_ref()
+            .is_some_and(|forecast| forecast.stale));
+        assert!(state.error.is_some());
+
+        state.update(Ok(forecast(7200)), Duration::from_secs(3600));
+        state.update(failed(), Duration::from_secs(3600));
+        assert!(state.forecast.is_none());
+    }
+
+    #[test]
+    fn retries_failed_locations_with_backoff() {
+        let mut state = source(&env::temp_dir(), true, 0, 0).state();
+        let interval = Duration::from_secs(3600);
+        assert!(state.is_due(interval));
+
+        let failed = || Err(NetworkError::Timeout.into());
+        state.update(failed(), interval);
+        assert!(!state.is_due(interval));
+        assert_eq!(state.wait(interval), Duration::from_secs(60));
+        state.update(failed(), interval);
+        state.update(failed(), interval);
+        assert_eq!(state.wait(interval), Duration::from_secs(240));
+        state.failures = 40;
+        assert_eq!(state.wait(interval), interval);
+
+        let forecast = Forecast {
+            weather: Weather::default(),
+            fetched_at: SystemTime::now(),
+            stale: false,
+            warning: None,
+        };
+        state.update(Ok(forecast), interval);
+        assert_eq!(state.failures, 0);
+        assert_eq!(state.wait(interval), interval);
+    }
+
+    fn collect(values: &[&str]) -> Vec<String> {
+        collect_locations(&values.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn keeps_one_location_per_value() {
+        assert_eq!(collect(&["Amsterdam", "Berlin"]), ["Amsterdam", "Berlin"]);
+        assert_eq!(collect(&["Berlin, Germany"]), ["Berlin, Germany"]);
+        assert_eq!(collect(&["52.37,4.89", " Paris "]), ["52.37,4.89", "Paris"]);
+    }
+
+    #[test]
+    fn defaults_to_ip_location() {
+        assert_eq!(collect(&[]), [""]);
+        assert_eq!(collect(&[""]), [""]);
+    }
+}
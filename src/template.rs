@@ -0,0 +1,282 @@
+use std::fs::read_to_string;
+
+use serde_json::Value;
+
+use crate::error::Error;
+use crate::expr::Expr;
+use crate::model::text_value;
+
+/// A parsed `--tooltip-template` or `--custom-indicator`. `{key}` is replaced
+/// by a value (or the result of an [`Expr`]),
+/// `{#key}...{/key}` repeats its content for every item of a list (or shows it
+/// once when the value is set) and `{^key}...{/key}` shows its content only
+/// when the value is missing or empty. Inside a section, the keys of the
+/// current item are looked up first. A placeholder that doesn't parse is
+/// shown as nothing, like a missing key, and kept in [`Template::warnings`].
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+    warnings: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Value(Expr),
+    Section {
+        name: String,
+        inverted: bool,
+        body: Vec<Node>,
+    },
+}
+
+/// Read a template given on the command line, either inline or from the file
+/// after an `@`. `option` names it in errors.
+pub fn load(option: &str, argument: &str) -> Result<Template, Error> {
+    let source = match argument.strip_prefix('@') {
+        Some(path) => {
+            read_to_string(path).map_err(|err| Error::Config(format!("{}: {}", path, err)))?
+        }
+        None => argument.to_string(),
+    };
+    Template::parse(&source).map_err(|err| Error::Config(format!("{}: {}", option, err)))
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Self, String> {
+        // The sections that are still open, with the nodes before them.
+        let mut stack: Vec<(String, bool, Vec<Node>)> = vec![];
+        let mut nodes = vec![];
+        let mut warnings = vec![];
+        let mut rest = source;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                nodes.push(Node::Text(rest[..start].to_string()));
+            }
+            let offset = source.len() - rest.len() + start;
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed {{ at position {}", offset))?;
+            let tag = rest[start + 1..start + end].trim();
+            rest = &rest[start + end + 1..];
+
+            // A section tag alone on its line doesn't leave an empty line.
+            if tag.starts_with(['#', '^', '/']) {
+                let before = &source[..offset];
+                let indent = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+                let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+                if indent.trim().is_empty() && rest[..line_end].trim().is_empty() {
+                    if let Some(Node::Text(text)) = nodes.last_mut() {
+                        text.truncate(text.len() - indent.len());
+                    }
+                    rest = &rest[line_end..];
+                }
+            }
+
+            if let Some(name) = tag.strip_prefix('#') {
+                stack.push((name.trim().to_string(), false, nodes));
+                nodes = vec![];
+            } else if let Some(name) = tag.strip_prefix('^') {
+                stack.push((name.trim().to_string(), true, nodes));
+                nodes = vec![];
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                match stack.pop() {
+                    Some((open, inverted, parent)) if open == name => {
+                        let body = std::mem::replace(&mut nodes, parent);
+                        nodes.push(Node::Section {
+                            name: open,
+                            inverted,
+                            body,
+                        });
+                    }
+                    Some((open, _, _)) => {
+                        return Err(format!("{{/{}}} closes {{#{}}}", name, open));
+                    }
+                    None => return Err(format!("{{/{}}} was never opened", name)),
+                }
+            } else if tag.is_empty() {
+                warnings.push(format!("empty placeholder at position {}", offset));
+            } else {
+                match Expr::parse(tag) {
+                    Ok(expr) => nodes.push(Node::Value(expr)),
+                    Err(err) => warnings.push(format!("in {{{}}}: {}", tag, err)),
+                }
+            }
+        }
+        if !rest.is_empty() {
+            nodes.push(Node::Text(rest.to_string()));
+        }
+        if let Some((open, _, _)) = stack.pop() {
+            return Err(format!("{{#{}}} is never closed", open));
+        }
+        Ok(Template { nodes, warnings })
+    }
+
+    pub fn render(&self, data: &Value) -> String {
+        let mut output = String::new();
+        render_nodes(&self.nodes, &mut vec![data], &mut output);
+        output
+    }
+
+    /// The placeholders that didn't parse.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+fn render_nodes<'a>(nodes: &'a [Node], scopes: &mut Vec<&'a Value>, output: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Value(expr) => {
+                output.push_str(&expr.eval(&|key| lookup(scopes, key).map(to_string)))
+            }
+            Node::Section {
+                name,
+                inverted,
+                body,
+            } => {
+                let value = lookup(scopes, name);
+                if *inverted {
+                    if !value.is_some_and(is_set) {
+                        render_nodes(body, scopes, output);
+                    }
+                    continue;
+                }
+                let items = match value {
+                    Some(Value::Array(items)) => items.iter().collect(),
+                    Some(value) if is_set(value) => vec![value],
+                    _ => vec![],
+                };
+                for item in items {
+                    scopes.push(item);
+                    render_nodes(body, scopes, output);
+                    scopes.pop();
+                }
+            }
+        }
+    }
+}
+
+/// The value of `key` in the innermost scope that has it. Dots separate the
+/// keys of nested objects, like `today.maxtempC`.
+fn lookup<'a>(scopes: &[&'a Value], key: &str) -> Option<&'a Value> {
+    let mut path = key.split('.');
+    let first = path.next()?;
+    let mut value = scopes.iter().rev().find_map(|scope| scope.get(first))?;
+    for key in path {
+        value = value.get(key)?;
+    }
+    Some(value)
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(set) => *set,
+        Value::String(text) => !text.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Number(_) | Value::Object(_) => true,
+    }
+}
+
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::Number(number) => number.to_string(),
+        Value::Bool(set) => set.to_string(),
+        value => text_value(value).unwrap_or_default().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(source: &str, data: Value) -> String {
+        Template::parse(source).unwrap().render(&data)
+    }
+
+    #[test]
+    fn replaces_values() {
+        let data = json!({"temp_C": "7", "areaName": [{"value": "London"}]});
+        assert_eq!(
+            render("{temp_C}° in {areaName}{missing}", data),
+            "7° in London"
+        );
+    }
+
+    #[test]
+    fn loops_over_nested_sections() {
+        let data = json!({
+            "unit": "°",
+            "days": [
+                {"DATE": "Mon", "hours": [{"tempC": "1"}, {"tempC": "2"}]},
+                {"DATE": "Tue", "hours": []},
+            ]
+        });
+        assert_eq!(
+            render(
+                "{#days}{DATE}:{#hours} {tempC}{unit}{/hours}{^hours} -{/hours}\n{/days}",
+                data
+            ),
+            "Mon: 1° 2°\nTue: -\n"
+        );
+    }
+
+    #[test]
+    fn drops_lines_with_only_a_section_tag() {
+        let data = json!({"days": [{"DATE": "Mon"}, {"DATE": "Tue"}]});
+        assert_eq!(
+            render("Forecast\n{#days}\n  {DATE}\n  {/days}\nEnd", data),
+            "Forecast\n  Mon\n  Tue\nEnd"
+        );
+    }
+
+    #[test]
+    fn shows_sections_for_set_values() {
+        let data = json!({"AS_OF": "As of 10:00", "STALE": false});
+        assert_eq!(
+            render("{#AS_OF}<i>{AS_OF}</i>{/AS_OF}{#STALE}!{/STALE}", data),
+            "<i>As of 10:00</i>"
+        );
+    }
+
+    #[test]
+    fn reports_unbalanced_sections() {
+        assert!(Template::parse("{#days}{DATE}").is_err());
+        assert!(Template::parse("{#days}{/hours}").is_err());
+        assert!(Template::parse("{/days}").is_err());
+        assert!(Template::parse("{temp_C").is_err());
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let data = json!({"temp_C": "7", "FeelsLikeC": "4", "precipMM": "0.0"});
+        assert_eq!(
+            render(
+                "{temp_C - FeelsLikeC}°{precipMM>0?☔:}{uvIndex|default:-}",
+                data
+            ),
+            "3°-"
+        );
+    }
+
+    #[test]
+    fn shows_invalid_placeholders_as_nothing() {
+        let template = Template::parse("{ICON} {temp_C}°{ }{temp_C -}").unwrap();
+        assert_eq!(
+            template.render(&json!({"ICON": "☀️", "temp_C": "7"})),
+            "☀️ 7°"
+        );
+        assert_eq!(
+            template.warnings(),
+            [
+                "empty placeholder at position 17",
+                "in {temp_C -}: expected a key or a number at the end"
+            ]
+        );
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs;
+
+use crate::constants::{TIME_ZONE_COORDINATES, TIME_ZONE_LINKS};
+
+/// The `--location` that guesses the city from the system time zone.
+pub const AUTO_TZ: &str = "auto-tz";
+
+/// Find the city of the system time zone as `lat,lon`, like `52.37,4.9` for
+/// Amsterdam with `Europe/Amsterdam`, without going online.
+pub fn locate() -> Result<String, String> {
+    let zone = system_zone().ok_or("cannot read the time zone from /etc/localtime")?;
+    zone_location(&zone).ok_or_else(|| format!("{} is not the time zone of a city", zone))
+}
+
+/// The zone name, like `Europe/Amsterdam`, from the `/etc/localtime` link or
+/// Debian's `/etc/timezone` when it's a copy.
+fn system_zone() -> Option<String> {
+    let from_link = fs::read_link("/etc/localtime")
+        .ok()
+        .and_then(|target| zone_from_path(&target.to_string_lossy()));
+    from_link.or_else(|| {
+        fs::read_to_string("/etc/timezone")
+            .ok()
+            .map(|zone| zone.trim().to_string())
+            .filter(|zone| !zone.is_empty())
+    })
+}
+
+fn zone_from_path(path: &str) -> Option<String> {
+    path.split_once("zoneinfo/")
+        .map(|(_, zone)| zone.trim_start_matches("posix/").to_string())
+}
+
+/// Zones missing from the table, like `Etc/UTC`, aren't anywhere in
+/// particular.
+fn zone_location(zone: &str) -> Option<String> {
+    let zone = TIME_ZONE_LINKS
+        .iter()
+        .find(|(link, _)| *link == zone)
+        .map_or(zone, |(_, target)| *target);
+    TIME_ZONE_COORDINATES
+        .iter()
+        .find(|(name, _, _)| *name == zone)
+        .map(|(_, lat, lon)| format!("{},{}", lat, lon))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_the_city_of_a_zone() {
+        assert_eq!(
+            zone_from_path("/usr/share/zoneinfo/Europe/Amsterdam").as_deref(),
+            Some("Europe/Amsterdam")
+        );
+        assert_eq!(
+            zone_from_path("../usr/share/zoneinfo/posix/Asia/Tokyo").as_deref(),
+            Some("Asia/Tokyo")
+        );
+        assert_eq!(
+            zone_location("Europe/Amsterdam").as_deref(),
+            Some("52.37,4.9")
+        );
+        assert_eq!(
+            zone_location("America/Argentina/Buenos_Aires").as_deref(),
+            Some("-34.6,-58.45")
+        );
+        // Knox, Indiana, not one of the other places called Knox.
+        assert_eq!(
+            zone_location("America/Indiana/Knox").as_deref(),
+            Some("41.3,-86.62")
+        );
+        assert_eq!(zone_location("Europe/Kiev"), zone_location("Europe/Kyiv"));
+        assert_eq!(
+            zone_location("US/Eastern"),
+            zone_location("America/New_York")
+        );
+        assert_eq!(zone_location("Etc/UTC"), None);
+        assert_eq!(zone_location("UTC"), None);
+        assert_eq!(zone_location("Mars/Olympus_Mons"), None);
+    }
+}
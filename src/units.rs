@@ -0,0 +1,270 @@
+use clap::ValueEnum;
+
+use crate::cli::Args;
+use crate::format::format_rounded;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum UnitSystem {
+    /// °C, km/h, hPa, mm and km.
+    Metric,
+    /// °F, mph, inHg, in and mi.
+    Imperial,
+    /// °C, mph, hPa, mm and mi.
+    Uk,
+    /// °C, m/s, hPa, mm and km.
+    Si,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum WindUnit {
+    Kmh,
+    Mph,
+    Ms,
+    Knots,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PressureUnit {
+    Hpa,
+    Inhg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum PrecipitationUnit {
+    Mm,
+    In,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum DistanceUnit {
+    Km,
+    Mi,
+}
+
+/// The unit for every measurement, from `--units` and the per-quantity
+/// options. Values are converted from the metric j1 keys unless the response
+/// already has them in the right unit.
+#[derive(Debug, Clone, Copy)]
+pub struct Units {
+    pub temperature: TemperatureUnit,
+    pub wind: WindUnit,
+    pub pressure: PressureUnit,
+    pub precipitation: PrecipitationUnit,
+    pub visibility: DistanceUnit,
+}
+
+impl Units {
+    pub fn from_args(args: &Args) -> Self {
+        let mut units = match args.units {
+            UnitSystem::Metric => Units {
+                temperature: TemperatureUnit::Celsius,
+                wind: WindUnit::Kmh,
+                pressure: PressureUnit::Hpa,
+                precipitation: PrecipitationUnit::Mm,
+                visibility: DistanceUnit::Km,
+            },
+            UnitSystem::Imperial => Units {
+                temperature: TemperatureUnit::Fahrenheit,
+                wind: WindUnit::Mph,
+                pressure: PressureUnit::Inhg,
+                precipitation: PrecipitationUnit::In,
+                visibility: DistanceUnit::Mi,
+            },
+            UnitSystem::Uk => Units {
+                temperature: TemperatureUnit::Celsius,
+                wind: WindUnit::Mph,
+                pressure: PressureUnit::Hpa,
+                precipitation: PrecipitationUnit::Mm,
+                visibility: DistanceUnit::Mi,
+            },
+            UnitSystem::Si => Units {
+                temperature: TemperatureUnit::Celsius,
+                wind: WindUnit::Ms,
+                pressure: PressureUnit::Hpa,
+                precipitation: PrecipitationUnit::Mm,
+                visibility: DistanceUnit::Km,
+            },
+        };
+        if args.fahrenheit {
+            units.temperature = TemperatureUnit::Fahrenheit;
+        }
+        if args.mph {
+            units.wind = WindUnit::Mph;
+        }
+        units.temperature = args.temperature_unit.unwrap_or(units.temperature);
+        units.wind = args.wind_unit.unwrap_or(units.wind);
+        units.pressure = args.pressure_unit.unwrap_or(units.pressure);
+        units.precipitation = args.precipitation_unit.unwrap_or(units.precipitation);
+        units.visibility = args.visibility_unit.unwrap_or(units.visibility);
+        units
+    }
+
+    /// The j1 key holding `key` in the selected unit, for `--main-indicator`.
+    /// `windspeedMs` and `windspeedKnots` aren't part of the response, see
+    /// [`Units::wind_keys`].
+    pub fn key<'a>(&self, key: &'a str) -> &'a str {
+        match key {
+            "temp_C" if self.temperature == TemperatureUnit::Fahrenheit => "temp_F",
+            "FeelsLikeC" if self.temperature == TemperatureUnit::Fahrenheit => "FeelsLikeF",
+            "windspeedKmph" => match self.wind {
+                WindUnit::Kmh => key,
+                WindUnit::Mph => "windspeedMiles",
+                WindUnit::Ms => "windspeedMs",
+                WindUnit::Knots => "windspeedKnots",
+            },
+            "pressure" if self.pressure == PressureUnit::Inhg => "pressureInches",
+            "precipMM" if self.precipitation == PrecipitationUnit::In => "precipInches",
+            "visibility" if self.visibility == DistanceUnit::Mi => "visibilityMiles",
+            key => key,
+        }
+    }
+
+    /// The wind speed keys wttr.in doesn't provide, computed from km/h.
+    pub fn wind_keys(kmph: Option<&str>) -> Vec<(&'static str, String)> {
+        let Some(kmph) = kmph.and_then(parse) else {
+            return vec![];
+        };
+        vec![
+            ("windspeedMs", format_rounded(kmph / 3.6, 0)),
+            ("windspeedKnots", format_rounded(kmph / 1.852, 0)),
+        ]
+    }
+
+    pub fn temperature(&self, celsius: Option<&str>, fahrenheit: Option<&str>) -> Option<String> {
+        match self.temperature {
+            TemperatureUnit::Celsius => celsius.map(str::to_string),
+            TemperatureUnit::Fahrenheit => fahrenheit
+                .map(str::to_string)
+                .or_else(|| Some(format_rounded(parse(celsius?)? * 9.0 / 5.0 + 32.0, 0))),
+        }
+    }
+
+    /// The wind speed followed by its unit.
+    pub fn wind(&self, kmph: Option<&str>, miles: Option<&str>) -> Option<String> {
+        let speed = match self.wind {
+            WindUnit::Kmh => kmph.map(str::to_string),
+            WindUnit::Mph => miles
+                .map(str::to_string)
+                .or_else(|| Some(format_rounded(parse(kmph?)? / 1.609344, 0))),
+            WindUnit::Ms => Some(format_rounded(parse(kmph?)? / 3.6, 0)),
+            WindUnit::Knots => Some(format_rounded(parse(kmph?)? / 1.852, 0)),
+        }?;
+        let label = match self.wind {
+            WindUnit::Kmh => "km/h",
+            WindUnit::Mph => "mph",
+            WindUnit::Ms => "m/s",
+            WindUnit::Knots => "kn",
+        };
+        Some(format!("{} {}", speed, label))
+    }
+
+    /// The pressure followed by its unit.
+    pub fn pressure(&self, hpa: Option<&str>, inches: Option<&str>) -> Option<String> {
+        Some(match self.pressure {
+            PressureUnit::Hpa => format!("{} hPa", hpa?),
+            PressureUnit::Inhg => format!(
+                "{} inHg",
+                inches
+                    .map(str::to_string)
+                    .or_else(|| Some(format_rounded(parse(hpa?)? * 0.02953, 2)))?
+            ),
+        })
+    }
+
+    /// The precipitation followed by its unit.
+    pub fn precipitation(&self, mm: Option<&str>, inches: Option<&str>) -> Option<String> {
+        Some(match self.precipitation {
+            PrecipitationUnit::Mm => format!("{} mm", mm?),
+            PrecipitationUnit::In => format!(
+                "{} in",
+                inches
+                    .map(str::to_string)
+                    .or_else(|| Some(format_rounded(parse(mm?)? / 25.4, 2)))?
+            ),
+        })
+    }
+
+    /// The visibility followed by its unit.
+    pub fn visibility(&self, km: Option<&str>, miles: Option<&str>) -> Option<String> {
+        Some(match self.visibility {
+            DistanceUnit::Km => format!("{} km", km?),
+            DistanceUnit::Mi => format!(
+                "{} mi",
+                miles
+                    .map(str::to_string)
+                    .or_else(|| Some(format_rounded(parse(km?)? / 1.609344, 0)))?
+            ),
+        })
+    }
+}
+
+fn parse(value: &str) -> Option<f64> {
+    value.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn units(cli: &[&str]) -> Units {
+        Units::from_args(&Args::parse_from([&["wttrbar"], cli].concat()))
+    }
+
+    #[test]
+    fn picks_units_from_system_and_overrides() {
+        let uk = units(&["--units", "uk"]);
+        assert_eq!(uk.temperature, TemperatureUnit::Celsius);
+        assert_eq!(uk.wind, WindUnit::Mph);
+        assert_eq!(uk.visibility, DistanceUnit::Mi);
+
+        let custom = units(&[
+            "--units",
+            "imperial",
+            "--wind-unit",
+            "knots",
+            "--pressure-unit",
+            "hpa",
+        ]);
+        assert_eq!(custom.temperature, TemperatureUnit::Fahrenheit);
+        assert_eq!(custom.wind, WindUnit::Knots);
+        assert_eq!(custom.pressure, PressureUnit::Hpa);
+
+        assert_eq!(
+            units(&["--fahrenheit"]).temperature,
+            TemperatureUnit::Fahrenheit
+        );
+    }
+
+    #[test]
+    fn converts_missing_values() {
+        let si = units(&["--units", "si"]);
+        assert_eq!(si.wind(Some("18"), Some("11")).as_deref(), Some("5 m/s"));
+        assert_eq!(si.key("windspeedKmph"), "windspeedMs");
+
+        let imperial = units(&["--units", "imperial"]);
+        assert_eq!(
+            imperial.temperature(Some("-5"), None).as_deref(),
+            Some("23")
+        );
+        assert_eq!(
+            imperial.temperature(Some("-5"), Some("22")).as_deref(),
+            Some("22")
+        );
+        assert_eq!(
+            imperial.pressure(Some("1013"), None).as_deref(),
+            Some("29.91 inHg")
+        );
+        assert_eq!(
+            imperial.precipitation(Some("0.2"), None).as_deref(),
+            Some("0.01 in")
+        );
+        assert_eq!(imperial.key("visibility"), "visibilityMiles");
+    }
+}